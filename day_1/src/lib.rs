@@ -0,0 +1,53 @@
+/// Base fuel requirements for a single module.
+pub fn fuel(mass: u64) -> u64 {
+    (mass / 3).saturating_sub(2)
+}
+
+/// Complete fuel requirements for a single module, including the fuel for the fuel.
+pub fn total_fuel(mass: u64) -> u64 {
+    std::iter::successors(Some(fuel(mass)), |&step| Some(fuel(step)))
+        .take_while(|&step| step > 0)
+        .sum()
+}
+
+pub fn fuel_for_modules(masses: &[u64]) -> u64 {
+    masses.iter().copied().map(total_fuel).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_masses(data: &str) -> Vec<u64> {
+        data.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn examples_fuel() {
+        assert_eq!(fuel(12), 2);
+        assert_eq!(fuel(14), 2);
+        assert_eq!(fuel(1969), 654);
+        assert_eq!(fuel(100756), 33583);
+        assert_eq!(fuel(2), 0);
+    }
+
+    #[test]
+    fn examples_total_fuel() {
+        assert_eq!(total_fuel(14), 2);
+        assert_eq!(total_fuel(1969), 966);
+        assert_eq!(total_fuel(100756), 50346);
+        assert_eq!(fuel_for_modules(&[14, 1969, 100756]), 51314);
+    }
+
+    #[test]
+    fn day_1_part_1() {
+        let masses = read_masses(include_str!("../tests/input"));
+        assert_eq!(masses.iter().copied().map(fuel).sum::<u64>(), 3372695);
+    }
+
+    #[test]
+    fn day_1_part_2() {
+        let masses = read_masses(include_str!("../tests/input"));
+        assert_eq!(fuel_for_modules(&masses), 5056172);
+    }
+}
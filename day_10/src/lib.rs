@@ -1,10 +1,12 @@
 use itertools::Itertools;
 use num::abs;
 use num::traits::Signed;
+use num::ToPrimitive;
 use num_integer::Integer;
 use num_rational::Ratio;
+use snafu::Snafu;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -17,6 +19,12 @@ pub enum Quadrant {
     TopLeft,
 }
 
+/// A direction, as a quadrant and the tangent of the angle from the quadrant's
+/// first axis.
+///
+/// Two angles are equal iff the offsets they were built from are collinear and
+/// point the same way, as long as the slope is reduced: build angles with `new`
+/// rather than with an unreduced `Ratio`.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Angle<T: Clone + Integer> {
     pub quadrant: Quadrant,
@@ -30,13 +38,53 @@ impl<T: Clone + Integer> PartialOrd for Angle<T> {
 }
 impl<T: Clone + Integer> Ord for Angle<T> {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.quadrant.cmp(&other.quadrant) {
-            Ordering::Equal => self.slope.cmp(&other.slope), //.reverse(),
-            other => other,
+        self.clockwise_key().cmp(&other.clockwise_key())
+    }
+}
+
+impl<T: Clone + Integer> Angle<T> {
+    /// A key increasing clockwise from north.
+    ///
+    /// Within each quadrant the slope is the tangent of the angle from the
+    /// quadrant's first axis, so it grows clockwise in all four of them.
+    pub fn clockwise_key(&self) -> (Quadrant, Ratio<T>) {
+        (self.quadrant, self.slope.clone())
+    }
+
+    /// The angle pointing north.
+    pub fn up() -> Self {
+        Angle {
+            quadrant: Quadrant::TopRight,
+            slope: Ratio::from_integer(T::zero()),
         }
     }
 }
 
+impl<T: Clone + Integer + Signed> Angle<T> {
+    /// The angle in `quadrant` with slope `numer / denom`, reduced and ignoring
+    /// signs.
+    pub fn new(quadrant: Quadrant, numer: T, denom: T) -> Self {
+        Angle {
+            quadrant,
+            slope: Ratio::new(numer.abs(), denom.abs()),
+        }
+    }
+}
+
+impl<T: Clone + Integer + ToPrimitive> Angle<T> {
+    /// Clockwise bearing from north, in degrees.
+    pub fn to_degrees(&self) -> f64 {
+        let base = match self.quadrant {
+            Quadrant::TopRight => 0.0,
+            Quadrant::BottomRight => 90.0,
+            Quadrant::BottomLeft => 180.0,
+            Quadrant::TopLeft => 270.0,
+        };
+        let slope = self.slope.numer().to_f64().unwrap() / self.slope.denom().to_f64().unwrap();
+        base + slope.atan().to_degrees()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Point<T> {
     pub x: T,
@@ -49,27 +97,25 @@ impl<T: Copy + Integer + Signed> Point<T> {
         let y = other.y - self.y;
         match (x.cmp(&T::zero()), y.cmp(&T::zero())) {
             (Ordering::Greater, Ordering::Greater) | (Ordering::Greater, Ordering::Equal) => {
-                Angle {
-                    quadrant: Quadrant::BottomRight,
-                    slope: Ratio::new(y, x),
-                }
+                Angle::new(Quadrant::BottomRight, y, x)
+            }
+            (Ordering::Less, Ordering::Greater) | (Ordering::Equal, Ordering::Greater) => {
+                Angle::new(Quadrant::BottomLeft, x, y)
+            }
+            (Ordering::Less, Ordering::Less) | (Ordering::Less, Ordering::Equal) => {
+                Angle::new(Quadrant::TopLeft, y, x)
+            }
+            (Ordering::Greater, Ordering::Less) | (Ordering::Equal, Ordering::Less) => {
+                Angle::new(Quadrant::TopRight, x, y)
             }
-            (Ordering::Less, Ordering::Greater) | (Ordering::Equal, Ordering::Greater) => Angle {
-                quadrant: Quadrant::BottomLeft,
-                slope: -Ratio::new(x, y),
-            },
-            (Ordering::Less, Ordering::Less) | (Ordering::Less, Ordering::Equal) => Angle {
-                quadrant: Quadrant::TopLeft,
-                slope: Ratio::new(y, x),
-            },
-            (Ordering::Greater, Ordering::Less) | (Ordering::Equal, Ordering::Less) => Angle {
-                quadrant: Quadrant::TopRight,
-                slope: -Ratio::new(x, y),
-            },
             (Ordering::Equal, Ordering::Equal) => panic!("Coincident points"),
         }
     }
 
+    /// Number of steps along the reduced slope from `self` to `other`.
+    ///
+    /// This only orders points sharing the same angle: see `euclidean` for the
+    /// geometric distance.
     pub fn distance_from(&self, other: &Self) -> T {
         let angle = self.angle_with(other);
         (match angle.quadrant {
@@ -79,49 +125,136 @@ impl<T: Copy + Integer + Signed> Point<T> {
     }
 }
 
-pub trait AsteroidMap<T> {
-    fn read(data: &str) -> Self;
+impl<T: Copy + Integer> Point<T> {
+    /// Pack the coordinates in a single number, as `x * factor + y`.
+    pub fn encode_xy(&self, factor: T) -> T {
+        self.x * factor + self.y
+    }
+}
+
+impl<T: Copy + ToPrimitive> Point<T> {
+    /// The geometric distance between the points, unlike `distance_from`.
+    pub fn euclidean(&self, other: &Self) -> f64 {
+        let x = self.x.to_f64().unwrap() - other.x.to_f64().unwrap();
+        let y = self.y.to_f64().unwrap() - other.y.to_f64().unwrap();
+        x.hypot(y)
+    }
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum MapError {
+    #[snafu(display("Unexpected {:?} at row {}, column {}", ch, row, col))]
+    UnexpectedChar { row: usize, col: usize, ch: char },
+}
+
+pub trait AsteroidMap<T>: Sized {
+    fn try_read(data: &str) -> Result<Self, MapError>;
+    fn read(data: &str) -> Self {
+        Self::read_with(data, '#')
+    }
+    /// Read a map where `asteroid` marks the asteroids and any other character
+    /// is empty space.
+    fn read_with(data: &str, asteroid: char) -> Self;
+    /// Number of asteroids directly visible from `point`, which doesn't need to
+    /// be an asteroid itself.
+    fn visible_from(&self, point: &Point<T>) -> usize;
+    /// All the asteroids from which the most asteroids are visible, in reading
+    /// order.
+    fn all_best(&self) -> Vec<(&Point<T>, usize)>;
     fn best(&self) -> Option<(&Point<T>, usize)>;
+    /// All the pairs of asteroids that can see each other, each listed once.
+    fn mutually_visible(&self) -> Vec<(&Point<T>, &Point<T>)>;
 }
 
 impl<T: Copy + TryFrom<usize, Error = impl Debug> + Integer + Signed + Hash> AsteroidMap<T>
     for Vec<Point<T>>
 {
-    fn read(data: &str) -> Self {
+    fn try_read(data: &str) -> Result<Self, MapError> {
         data.lines()
             .enumerate()
             .flat_map(|(y, line)| {
-                line.chars().enumerate().filter_map(move |(x, c)| {
-                    if c == '#' {
-                        Some(Point {
-                            x: T::try_from(x).unwrap(),
-                            y: T::try_from(y).unwrap(),
-                        })
-                    } else {
-                        assert!(c == '.');
-                        None
-                    }
+                line.chars().enumerate().filter_map(move |(x, c)| match c {
+                    '#' => Some(Ok(Point {
+                        x: T::try_from(x).unwrap(),
+                        y: T::try_from(y).unwrap(),
+                    })),
+                    '.' => None,
+                    ch => Some(Err(MapError::UnexpectedChar { row: y, col: x, ch })),
                 })
             })
             .collect()
     }
 
-    fn best(&self) -> Option<(&Point<T>, usize)> {
-        self.iter()
-            .map(|candidate| {
-                let visible = self
-                    .iter()
-                    .filter_map(|point| {
-                        if point == candidate {
-                            None
-                        } else {
-                            Some(candidate.angle_with(point))
-                        }
+    fn read_with(data: &str, asteroid: char) -> Self {
+        data.lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == asteroid)
+                    .map(move |(x, _)| Point {
+                        x: T::try_from(x).unwrap(),
+                        y: T::try_from(y).unwrap(),
                     })
-                    .collect::<HashSet<_>>();
-                (candidate, visible.len())
             })
-            .max_by_key(|(_, count)| *count)
+            .collect()
+    }
+
+    fn visible_from(&self, point: &Point<T>) -> usize {
+        self.iter()
+            .filter_map(|other| {
+                if other == point {
+                    None
+                } else {
+                    Some(point.angle_with(other))
+                }
+            })
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn all_best(&self) -> Vec<(&Point<T>, usize)> {
+        let counts: Vec<_> = self
+            .iter()
+            .map(|candidate| (candidate, self.visible_from(candidate)))
+            .collect();
+        let max = counts.iter().map(|(_, count)| *count).max();
+        counts
+            .into_iter()
+            .filter(|(_, count)| Some(*count) == max)
+            .sorted_by_key(|(point, _)| (point.y, point.x))
+            .collect()
+    }
+
+    fn best(&self) -> Option<(&Point<T>, usize)> {
+        self.all_best().into_iter().next()
+    }
+
+    fn mutually_visible(&self) -> Vec<(&Point<T>, &Point<T>)> {
+        let mut pairs = Vec::new();
+        for (i, source) in self.iter().enumerate() {
+            let mut nearest: HashMap<Angle<T>, (usize, &Point<T>)> = HashMap::new();
+            for (j, other) in self
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| *other != source)
+            {
+                let entry = nearest
+                    .entry(source.angle_with(other))
+                    .or_insert((j, other));
+                if source.distance_from(other) < source.distance_from(entry.1) {
+                    *entry = (j, other);
+                }
+            }
+            pairs.extend(
+                nearest
+                    .into_values()
+                    .filter(|(j, _)| *j > i)
+                    .sorted_by_key(|(j, _)| *j)
+                    .map(|(_, other)| (source, other)),
+            );
+        }
+        pairs
     }
 }
 
@@ -131,10 +264,76 @@ pub fn vaporization_order<'a>(
     map: &'a [Point<isize>],
     source: &Point<isize>,
 ) -> Vec<&'a Point<isize>> {
-    let steps: Vec<Vec<_>> = map
+    vaporization_order_from(map, source, Angle::up(), true)
+}
+
+/// Order in which the asteroids are vaporized by a laser starting at `start`
+/// and rotating either clockwise or counterclockwise.
+pub fn vaporization_order_from<'a>(
+    map: &'a [Point<isize>],
+    source: &Point<isize>,
+    start: Angle<isize>,
+    clockwise: bool,
+) -> Vec<&'a Point<isize>> {
+    rounds(sweep(map, source, start, clockwise))
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// The asteroids vaporized during each full rotation of the laser, starting
+/// north and rotating clockwise.
+pub fn vaporization_rounds<'a>(
+    map: &'a [Point<isize>],
+    source: &Point<isize>,
+) -> Vec<Vec<&'a Point<isize>>> {
+    rounds(sweep(map, source, Angle::up(), true))
+}
+
+/// Take the nearest remaining asteroid of each group, one rotation at a time.
+fn rounds(steps: Vec<Vec<&Point<isize>>>) -> Vec<Vec<&Point<isize>>> {
+    let count = steps.iter().map(|vec| vec.len()).max().unwrap_or(0);
+    (0..count)
+        .map(|i| steps.iter().filter_map(|vec| vec.get(i)).copied().collect())
+        .collect()
+}
+
+/// The `n`-th asteroid vaporized by the laser, without computing the whole
+/// order.
+pub fn nth_vaporized<'a>(
+    map: &'a [Point<isize>],
+    source: &Point<isize>,
+    mut n: usize,
+) -> Option<&'a Point<isize>> {
+    let steps = sweep(map, source, Angle::up(), true);
+    for i in 0.. {
+        let mut rotation = steps.iter().filter_map(|vec| vec.get(i)).peekable();
+        rotation.peek()?;
+        match rotation.nth(n) {
+            Some(point) => return Some(*point),
+            None => n -= steps.iter().filter(|vec| vec.len() > i).count(),
+        }
+    }
+    unreachable!()
+}
+
+/// The `n`-th asteroid vaporized by the laser, encoded as `x * 100 + y`.
+pub fn nth_vaporized_xy(map: &[Point<isize>], source: &Point<isize>, n: usize) -> Option<isize> {
+    nth_vaporized(map, source, n).map(|point| point.encode_xy(100))
+}
+
+/// Group the asteroids by angle, in the order the laser reaches them, and
+/// each group by distance.
+fn sweep<'a>(
+    map: &'a [Point<isize>],
+    source: &Point<isize>,
+    start: Angle<isize>,
+    clockwise: bool,
+) -> Vec<Vec<&'a Point<isize>>> {
+    let mut steps: Vec<Vec<_>> = map
         .iter()
         .filter(|point| point != &source)
-        .sorted_by_key(|point| source.angle_with(point))
+        .sorted_by_key(|point| source.angle_with(point).clockwise_key())
         .group_by(|point| source.angle_with(point))
         .into_iter()
         .map(|(_, points)| {
@@ -143,15 +342,22 @@ pub fn vaporization_order<'a>(
                 .collect()
         })
         .collect();
-    let mut result = Vec::with_capacity(map.len());
-    for i in 0..steps.iter().map(|vec| vec.len()).max().unwrap() {
-        for vec in &steps {
-            if let Some(point) = vec.get(i) {
-                result.push(*point);
-            }
-        }
+    if !clockwise {
+        steps.reverse();
     }
-    result
+    let first = steps
+        .iter()
+        .position(|points| {
+            let angle = source.angle_with(points[0]);
+            if clockwise {
+                angle >= start
+            } else {
+                angle <= start
+            }
+        })
+        .unwrap_or(0);
+    steps.rotate_left(first);
+    steps
 }
 
 #[cfg(test)]
@@ -171,6 +377,37 @@ mod tests {
         let (point, count) = map.best().unwrap();
         assert_eq!(count, 8);
         assert_eq!(point, &Point { x: 3, y: 4 });
+        assert_eq!(map.visible_from(&Point { x: 4, y: 2 }), 5);
+        assert_eq!(map.visible_from(&Point { x: 0, y: 0 }), 8);
+    }
+
+    #[test]
+    fn euclidean() {
+        let origin = Point { x: 0, y: 0 };
+        let point = Point { x: 3, y: 4 };
+        assert_eq!(origin.euclidean(&point), 5.0);
+        assert_eq!(point.euclidean(&origin), 5.0);
+        assert_eq!(origin.distance_from(&point), 1);
+    }
+
+    #[test]
+    fn mutually_visible() {
+        let map = AsteroidVec::read(
+            "\
+.#..#
+.....
+#####
+....#
+...##",
+        );
+        let pairs = map.mutually_visible();
+        let total: usize = map.iter().map(|point| map.visible_from(point)).sum();
+        assert_eq!(pairs.len(), total / 2);
+        assert_eq!(pairs.len(), 34);
+        assert!(pairs.contains(&(&Point { x: 1, y: 0 }, &Point { x: 4, y: 0 })));
+        assert!(!pairs
+            .iter()
+            .any(|pair| pair == &(&Point { x: 0, y: 2 }, &Point { x: 2, y: 2 })));
     }
 
     #[test]
@@ -263,6 +500,42 @@ mod tests {
         assert_eq!(point, &Point { x: 11, y: 13 });
     }
 
+    #[test]
+    fn unexpected_char() {
+        assert_eq!(
+            AsteroidVec::try_read(".#..#\n..?..\n#####"),
+            Err(MapError::UnexpectedChar {
+                row: 1,
+                col: 2,
+                ch: '?'
+            })
+        );
+    }
+
+    #[test]
+    fn custom_glyph() {
+        let map = AsteroidVec::read_with(".*..*\n.#...\n*****\n....*\n...**", '*');
+        assert_eq!(map.len(), 10);
+        assert!(!map.contains(&Point { x: 1, y: 1 }));
+        assert_eq!(map.best(), Some((&Point { x: 3, y: 4 }, 8)));
+    }
+
+    #[test]
+    fn ties() {
+        let map = AsteroidVec::read(
+            "\
+.#.
+.#.
+.#.
+.#.",
+        );
+        assert_eq!(
+            map.all_best(),
+            vec![(&Point { x: 1, y: 1 }, 2), (&Point { x: 1, y: 2 }, 2)]
+        );
+        assert_eq!(map.best(), Some((&Point { x: 1, y: 1 }, 2)));
+    }
+
     #[test]
     fn day_10_part_1() {
         let map = AsteroidVec::read(include_str!("input"));
@@ -345,6 +618,131 @@ mod tests {
         );
     }
 
+    #[test]
+    fn full_rotations() {
+        let map = AsteroidVec::read(
+            "\
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##",
+        );
+        let source = Point { x: 8, y: 3 };
+        let rounds = vaporization_rounds(&map, &source);
+        assert_eq!(rounds[0].len(), map.visible_from(&source));
+        assert!(rounds.windows(2).all(|w| w[0].len() >= w[1].len()));
+        assert_eq!(
+            rounds.into_iter().flatten().collect::<Vec<_>>(),
+            vaporization_order(&map, &source)
+        );
+    }
+
+    #[test]
+    fn sweep_start() {
+        let map = AsteroidVec::read(
+            "\
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##",
+        );
+        let source = Point { x: 8, y: 3 };
+        let east = Angle {
+            quadrant: Quadrant::BottomRight,
+            slope: Ratio::from_integer(0),
+        };
+        let order = vaporization_order_from(&map, &source, east, true);
+        assert_eq!(order[0], &Point { x: 12, y: 3 });
+        assert_eq!(order[1], &Point { x: 16, y: 4 });
+        assert_eq!(order.len(), map.len() - 1);
+        let order = vaporization_order_from(&map, &source, Angle::up(), false);
+        assert_eq!(order[0], &Point { x: 8, y: 1 });
+        assert_eq!(order[1], &Point { x: 7, y: 0 });
+    }
+
+    #[test]
+    fn all_quadrants() {
+        let source = Point { x: 5, y: 5 };
+        let clockwise = vec![
+            Point { x: 5, y: 0 },
+            Point { x: 6, y: 0 },
+            Point { x: 10, y: 4 },
+            Point { x: 10, y: 5 },
+            Point { x: 10, y: 6 },
+            Point { x: 6, y: 10 },
+            Point { x: 5, y: 10 },
+            Point { x: 4, y: 10 },
+            Point { x: 0, y: 6 },
+            Point { x: 0, y: 5 },
+            Point { x: 0, y: 4 },
+            Point { x: 4, y: 0 },
+        ];
+        let keys: Vec<_> = clockwise
+            .iter()
+            .map(|point| source.angle_with(point).clockwise_key())
+            .collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+        let mut map: Vec<_> = clockwise
+            .iter()
+            .rev()
+            .map(|p| Point { x: p.x, y: p.y })
+            .collect();
+        map.push(Point { x: 5, y: 5 });
+        let order = vaporization_order(&map, &source);
+        assert_eq!(order, clockwise.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn collinear_angles() {
+        let origin = Point { x: 0, y: 0 };
+        assert_eq!(
+            origin.angle_with(&Point { x: 2, y: 4 }),
+            origin.angle_with(&Point { x: 1, y: 2 })
+        );
+        assert_ne!(
+            origin.angle_with(&Point { x: 2, y: 4 }),
+            origin.angle_with(&Point { x: -1, y: -2 })
+        );
+        assert_eq!(
+            Angle::new(Quadrant::BottomRight, 4, 2),
+            Angle::new(Quadrant::BottomRight, -2, -1)
+        );
+        assert_eq!(
+            Angle::new(Quadrant::BottomRight, 4, 2),
+            origin.angle_with(&Point { x: 1, y: 2 })
+        );
+    }
+
+    #[test]
+    fn bearings() {
+        let map = AsteroidVec::read(
+            "\
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##",
+        );
+        let source = Point { x: 8, y: 3 };
+        let bearings: Vec<_> = vaporization_order(&map, &source)
+            .into_iter()
+            .map(|point| source.angle_with(point).to_degrees())
+            .collect();
+        assert!(bearings[0].abs() < 1e-9);
+        let rotation = map
+            .iter()
+            .filter(|point| *point != &source)
+            .map(|point| source.angle_with(point))
+            .collect::<HashSet<_>>()
+            .len();
+        assert!(bearings[..rotation].windows(2).all(|w| w[0] < w[1]));
+        assert!(bearings[rotation - 1] < 360.0);
+        assert!((source.angle_with(&Point { x: 9, y: 3 }).to_degrees() - 90.0).abs() < 1e-9);
+        assert!((source.angle_with(&Point { x: 7, y: 4 }).to_degrees() - 225.0).abs() < 1e-9);
+    }
+
     #[test]
     fn example7() {
         let map = AsteroidVec::read(
@@ -382,13 +780,20 @@ mod tests {
         assert_eq!(order[199], &Point { x: 8, y: 2 });
         assert_eq!(order[200], &Point { x: 10, y: 9 });
         assert_eq!(order[298], &Point { x: 11, y: 1 });
+        let source = map.best().unwrap().0;
+        for n in &[0, 1, 2, 9, 19, 49, 99, 198, 199, 200, 298] {
+            assert_eq!(nth_vaporized(&map, source, *n), Some(order[*n]));
+        }
+        assert_eq!(nth_vaporized(&map, source, 299), None);
     }
 
     #[test]
     fn day_10_part_2() {
         let map = AsteroidVec::read(include_str!("input"));
         let (point, _) = map.best().unwrap();
-        let order = vaporization_order(&map, point);
-        assert_eq!(order[199].x * 100 + order[199].y, 616);
+        assert_eq!(nth_vaporized_xy(&map, point, 199), Some(616));
+        let point = nth_vaporized(&map, point, 199).unwrap();
+        assert_eq!(point.encode_xy(100), 616);
+        assert_eq!(Point { x: 6, y: -16 }.encode_xy(10), 44);
     }
 }
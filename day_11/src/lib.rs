@@ -1,11 +1,11 @@
 extern crate day_9;
 
 use day_9::computer::Computer;
-use itertools::Itertools;
 use snafu::Snafu;
 use std::cell::RefCell;
 use std::collections::hash_set::HashSet;
 use std::convert::TryFrom;
+use util::grid::{bounds, Canvas, Coord, Direction, Turn};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -15,12 +15,6 @@ pub enum Error {
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
-}
-
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Color {
     Black,
@@ -48,57 +42,45 @@ impl From<Color> for isize {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-enum Direction {
-    North,
-    East,
-    South,
-    West,
-}
-
-impl Direction {
-    pub fn turn_left(self) -> Self {
-        match self {
-            Direction::North => Direction::East,
-            Direction::East => Direction::South,
-            Direction::South => Direction::West,
-            Direction::West => Direction::North,
-        }
-    }
-
-    pub fn turn_right(self) -> Self {
-        match self {
-            Direction::North => Direction::West,
-            Direction::East => Direction::North,
-            Direction::South => Direction::East,
-            Direction::West => Direction::South,
-        }
-    }
-}
-
 #[derive(Debug)]
 pub struct PaintingRobot {
-    position: Point,
+    position: Coord,
     direction: Direction,
-    whites: HashSet<Point>,
-    painted: HashSet<Point>,
+    whites: HashSet<Coord>,
+    painted: HashSet<Coord>,
+    visits: Vec<Coord>,
+    /// Width and height of the torus the robot moves on, if any.
+    torus: Option<(isize, isize)>,
 }
 
 impl PaintingRobot {
     pub fn new(starting_color: Color) -> Self {
-        let position = Point { x: 0, y: 0 };
+        Self::with_orientation(starting_color, Direction::North)
+    }
+
+    pub fn with_orientation(starting_color: Color, facing: Direction) -> Self {
+        let position = Coord { x: 0, y: 0 };
         let whites = match starting_color {
             Color::Black => HashSet::new(),
             Color::White => [position].iter().cloned().collect(),
         };
         PaintingRobot {
             position,
-            direction: Direction::North,
+            direction: facing,
             whites,
             painted: HashSet::new(),
+            visits: Vec::new(),
+            torus: None,
         }
     }
 
+    /// Make the robot wrap around the edges of a `width × height` grid with a
+    /// corner at the origin.
+    pub fn wrapping(mut self, width: isize, height: isize) -> Self {
+        self.torus = Some((width, height));
+        self
+    }
+
     pub fn current_color(&self) -> Color {
         if self.whites.contains(&self.position) {
             Color::White
@@ -111,36 +93,44 @@ impl PaintingRobot {
         self.painted.len()
     }
 
+    /// How many of the painted cells are currently white and black.
+    pub fn color_counts(&self) -> (usize, usize) {
+        let white = self.painted.intersection(&self.whites).count();
+        (white, self.painted.len() - white)
+    }
+
+    pub fn painted_cells(&self) -> impl Iterator<Item = &Coord> {
+        self.painted.iter()
+    }
+
+    /// The painted cells, in the order they were first painted.
+    pub fn visit_order(&self) -> &[Coord] {
+        &self.visits
+    }
+
     pub fn go_left(&mut self) {
-        self.direction = self.direction.turn_left();
+        self.direction = self.direction.turn(Turn::Left);
         self.advance();
     }
     pub fn go_right(&mut self) {
-        self.direction = self.direction.turn_right();
+        self.direction = self.direction.turn(Turn::Right);
         self.advance();
     }
 
     fn advance(&mut self) {
-        match self.direction {
-            Direction::North => self.position.y -= 1,
-            Direction::South => self.position.y += 1,
-            Direction::East => self.position.x -= 1,
-            Direction::West => self.position.x += 1,
-        }
+        self.position = match self.torus {
+            Some((width, height)) => self.position.wrapping_add(self.direction, width, height),
+            None => self.position + self.direction,
+        };
     }
 
     pub fn paint(&mut self, color: Color) {
-        match color {
-            Color::Black => {
-                if self.whites.remove(&self.position) {
-                    self.painted.insert(self.position);
-                }
-            }
-            Color::White => {
-                if self.whites.insert(self.position) {
-                    self.painted.insert(self.position);
-                }
-            }
+        let changed = match color {
+            Color::Black => self.whites.remove(&self.position),
+            Color::White => self.whites.insert(self.position),
+        };
+        if changed && self.painted.insert(self.position) {
+            self.visits.push(self.position);
         }
     }
 
@@ -168,20 +158,24 @@ impl PaintingRobot {
         .unwrap();
     }
 
+    /// The colors over the bounding box of the white cells.
+    pub fn to_grid(&self) -> Vec<Vec<Color>> {
+        let (min, max) = bounds(&self.whites).unwrap_or_default();
+        let width = (max.x - min.x + 1) as usize;
+        let height = (max.y - min.y + 1) as usize;
+        let mut data = vec![vec![Color::Black; width]; height];
+        for point in &self.whites {
+            data[(point.y - min.y) as usize][(point.x - min.x) as usize] = Color::White;
+        }
+        data
+    }
+
     pub fn draw(&self) -> String {
-        let min_x = self.whites.iter().map(|point| point.x).min().unwrap_or(0);
-        let max_x = self.whites.iter().map(|point| point.x).max().unwrap_or(0);
-        let min_y = self.whites.iter().map(|point| point.y).min().unwrap_or(0);
-        let max_y = self.whites.iter().map(|point| point.y).max().unwrap_or(0);
-        let width = (max_x - min_x + 1) as usize;
-        let height = (max_y - min_y + 1) as usize;
-        let mut data = vec![vec![' '; width]; height];
+        let mut canvas = Canvas::new();
         for point in &self.whites {
-            data[(point.y - min_y) as usize][(point.x - min_x) as usize] = '#';
+            canvas.set(point.x, point.y, '#');
         }
-        data.into_iter()
-            .map(|line| line.into_iter().collect::<String>())
-            .join("\n")
+        canvas.to_string()
     }
 }
 
@@ -218,6 +212,73 @@ mod tests {
         painter.go_left();
         // assert_eq!(painter.painted_count(), 6);  // Typo in the puzzle description.
         assert_eq!(painter.painted_count(), 5);
+        assert_eq!(painter.visit_order().len(), painter.painted_count());
+        let (white, black) = painter.color_counts();
+        assert_eq!(white + black, painter.painted_count());
+        assert_eq!((white, black), (4, 1));
+        assert_eq!(painter.visit_order()[0], Coord { x: 0, y: 0 });
+        assert_eq!(
+            painter.painted_cells().collect::<HashSet<_>>(),
+            painter.visit_order().iter().collect()
+        );
+    }
+
+    #[test]
+    fn turns_follow_grid_convention() {
+        let mut painter = PaintingRobot::new(Color::Black);
+        painter.go_left();
+        assert_eq!(painter.position, Coord { x: -1, y: 0 });
+        painter.go_right();
+        assert_eq!(painter.position, Coord { x: -1, y: -1 });
+    }
+
+    #[test]
+    fn torus() {
+        let mut painter =
+            PaintingRobot::with_orientation(Color::Black, Direction::North).wrapping(3, 3);
+        painter.go_right();
+        painter.go_left();
+        painter.go_right();
+        assert_eq!(painter.position, Coord { x: 2, y: 2 });
+        painter.go_left();
+        painter.go_right();
+        assert_eq!(painter.position, Coord { x: 0, y: 1 });
+    }
+
+    #[test]
+    fn orientation() {
+        let run = |facing| {
+            let mut painter = PaintingRobot::with_orientation(Color::Black, facing);
+            painter.paint(Color::White);
+            painter.go_left();
+            painter.paint(Color::Black);
+            painter.go_left();
+            painter.paint(Color::White);
+            painter.go_left();
+            painter.paint(Color::White);
+            painter.go_left();
+            painter.paint(Color::Black);
+            painter.go_right();
+            painter.paint(Color::White);
+            painter.go_left();
+            painter.paint(Color::White);
+            painter.go_left();
+            painter
+        };
+        let north = run(Direction::North);
+        let east = run(Direction::East);
+        assert_eq!(north.painted_count(), east.painted_count());
+        // Facing East is a right turn from North, so the whole path is rotated
+        // clockwise.
+        let rotate = |point: &Coord| Coord {
+            x: -point.y,
+            y: point.x,
+        };
+        assert_eq!(
+            east.visit_order(),
+            north.visit_order().iter().map(rotate).collect::<Vec<_>>()
+        );
+        assert_eq!(east.whites, north.whites.iter().map(rotate).collect());
     }
 
     #[test]
@@ -247,6 +308,12 @@ mod tests {
             .unwrap();
         let mut painter = PaintingRobot::new(Color::White);
         painter.execute(intcode);
+        let grid = painter.to_grid();
+        let drawing = painter.draw();
+        assert_eq!(grid.len(), drawing.lines().count());
+        for (row, line) in grid.iter().zip(drawing.lines()) {
+            assert_eq!(row.len(), line.len());
+        }
         assert_eq!(
             painter.draw(),
             " ##  ###  #### #  # ####  ##  ####  ## \n\
@@ -1,8 +1,28 @@
 use num::Integer;
 use num::Signed;
+use regex::Regex;
+use snafu::{ResultExt, Snafu};
 use std::iter::Sum;
 use std::ops::AddAssign;
 
+#[derive(Debug, Snafu)]
+pub enum ParseError {
+    #[snafu(display("Line {} is not a position: {:?}", line, text))]
+    LineInvalid { line: usize, text: String },
+
+    #[snafu(display("Invalid coordinate on line {}: {}", line, source))]
+    CoordinateInvalid {
+        line: usize,
+        source: std::num::ParseIntError,
+    },
+}
+
+#[derive(Debug, Snafu)]
+pub enum PeriodError {
+    #[snafu(display("The period doesn't fit in 128 bits"))]
+    Overflow,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StateSlice<T> {
     pub positions: Vec<T>,
@@ -18,6 +38,29 @@ where
         self.velocity_step();
     }
 
+    /// Like `step`, but afterwards merges bodies that are within `epsilon` of
+    /// each other and moving in opposite directions, summing their momentum.
+    pub fn step_with_merge(&mut self, epsilon: T) {
+        self.step();
+        let mut i = 0;
+        while i < self.positions.len() {
+            let mut j = i + 1;
+            while j < self.positions.len() {
+                if (self.positions[i] - self.positions[j]).abs() <= epsilon
+                    && !self.velocities[i].is_zero()
+                    && self.velocities[i].signum() == -self.velocities[j].signum()
+                {
+                    self.positions.remove(j);
+                    let velocity = self.velocities.remove(j);
+                    self.velocities[i] += velocity;
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+    }
+
     fn gravity_step(&mut self) {
         for (velocity, position) in self.velocities.iter_mut().zip(self.positions.iter()) {
             *velocity += self
@@ -34,7 +77,7 @@ where
         }
     }
 
-    pub fn find_period(&self) -> usize {
+    pub fn find_period(&self) -> u64 {
         let mut tmp = self.clone();
         for i in 1.. {
             tmp.step();
@@ -48,37 +91,63 @@ where
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct State<T> {
-    pub x: StateSlice<T>,
-    pub y: StateSlice<T>,
-    pub z: StateSlice<T>,
+    pub axes: Vec<StateSlice<T>>,
 }
 
 impl<T> State<T>
 where
     T: Integer + Signed + AddAssign + Sum + Copy,
 {
-    pub fn energy(&self) -> T {
-        let pot = self
-            .x
-            .positions
-            .iter()
-            .zip(self.y.positions.iter())
-            .zip(self.z.positions.iter())
-            .map(|((x, y), z)| x.abs() + y.abs() + z.abs());
-        let kin = self
-            .x
-            .velocities
+    /// Build a state at rest from the coordinates of each body.
+    pub fn from_positions(bodies: Vec<Vec<T>>) -> Self {
+        let dimensions = bodies.first().map_or(0, |body| body.len());
+        State {
+            axes: (0..dimensions)
+                .map(|axis| StateSlice {
+                    positions: bodies.iter().map(|body| body[axis]).collect(),
+                    velocities: vec![T::zero(); bodies.len()],
+                })
+                .collect(),
+        }
+    }
+
+    /// Potential energy of each body.
+    pub fn potential_energy(&self) -> Vec<T> {
+        self.body_energy(|axis| &axis.positions)
+    }
+
+    /// Kinetic energy of each body.
+    pub fn kinetic_energy(&self) -> Vec<T> {
+        self.body_energy(|axis| &axis.velocities)
+    }
+
+    fn body_energy(&self, values: impl Fn(&StateSlice<T>) -> &Vec<T>) -> Vec<T> {
+        let bodies = self.axes.first().map_or(0, |axis| values(axis).len());
+        (0..bodies)
+            .map(|body| self.axes.iter().map(|axis| values(axis)[body].abs()).sum())
+            .collect()
+    }
+
+    /// Sum of the velocities along each axis, which gravity conserves.
+    pub fn total_momentum(&self) -> Vec<T> {
+        self.axes
             .iter()
-            .zip(self.y.velocities.iter())
-            .zip(self.z.velocities.iter())
-            .map(|((x, y), z)| x.abs() + y.abs() + z.abs());
-        pot.zip(kin).map(|(p, k)| p * k).sum()
+            .map(|axis| axis.velocities.iter().copied().sum())
+            .collect()
+    }
+
+    pub fn energy(&self) -> T {
+        self.potential_energy()
+            .into_iter()
+            .zip(self.kinetic_energy())
+            .map(|(pot, kin)| pot * kin)
+            .sum()
     }
 
     pub fn step(&mut self) {
-        self.x.step();
-        self.y.step();
-        self.z.step();
+        for axis in self.axes.iter_mut() {
+            axis.step();
+        }
     }
 }
 
@@ -102,131 +171,297 @@ where
         self.state.step();
     }
 
-    pub fn find_period(&self) -> usize {
-        self.state
-            .x
-            .find_period()
-            .lcm(&self.state.y.find_period())
-            .lcm(&self.state.z.find_period())
+    /// Equivalent to calling `step` `n` times.
+    ///
+    /// Axes are independent, so each one is advanced on its own.
+    pub fn step_n(&mut self, n: usize) {
+        for axis in self.state.axes.iter_mut() {
+            for _ in 0..n {
+                axis.step();
+            }
+        }
+    }
+
+    /// Advance `steps` times, returning the total energy after each step.
+    pub fn run_recording(&mut self, steps: usize) -> Vec<T> {
+        (0..steps)
+            .map(|_| {
+                self.step();
+                self.state.energy()
+            })
+            .collect()
+    }
+
+    pub fn find_period(&self) -> Result<u128, PeriodError> {
+        checked_lcm(self.state.axes.iter().map(StateSlice::find_period))
     }
 }
 
+/// Total energy after `steps` steps from `state`, which is left untouched.
+pub fn energy_after<T>(state: &State<T>, steps: usize) -> T
+where
+    T: Integer + Signed + AddAssign + Sum + Copy,
+{
+    let mut sim = Simulator::new(state.clone());
+    sim.step_n(steps);
+    sim.state().energy()
+}
+
+fn checked_lcm(periods: impl IntoIterator<Item = u64>) -> Result<u128, PeriodError> {
+    periods.into_iter().try_fold(1, |acc: u128, period| {
+        let period = u128::from(period);
+        (acc / acc.gcd(&period))
+            .checked_mul(period)
+            .ok_or(PeriodError::Overflow)
+    })
+}
+
+/// Read one `<x=.., y=.., z=..>` position per line, with the bodies at rest.
+pub fn parse_state(data: &str) -> Result<State<i64>, ParseError> {
+    let re = Regex::new(r"^<x=(-?\d+), y=(-?\d+), z=(-?\d+)>$").unwrap();
+    let bodies = data
+        .lines()
+        .enumerate()
+        .map(|(index, text)| {
+            let line = index + 1;
+            let caps = re
+                .captures(text.trim())
+                .ok_or_else(|| ParseError::LineInvalid {
+                    line,
+                    text: text.to_owned(),
+                })?;
+            (1..=3)
+                .map(|i| caps[i].parse().context(CoordinateInvalid { line }))
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(State::from_positions(bodies))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use regex::Regex;
+
+    #[test]
+    fn momentum() {
+        let mut sim = Simulator::new(State::from_positions(vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ]));
+        for _ in 0..100 {
+            sim.step();
+            assert_eq!(sim.state().total_momentum(), vec![0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let mut slice = StateSlice {
+            positions: vec![0, 4],
+            velocities: vec![1, -1],
+        };
+        slice.step_with_merge(0);
+        assert_eq!(
+            slice,
+            StateSlice {
+                positions: vec![2],
+                velocities: vec![0],
+            }
+        );
+    }
 
     #[test]
     fn example1() {
         let mut sim = Simulator::new(State {
-            x: StateSlice {
-                positions: vec![-1, 2, 4, 3],
-                velocities: vec![0; 4],
-            },
-            y: StateSlice {
-                positions: vec![0, -10, -8, 5],
-                velocities: vec![0; 4],
-            },
-            z: StateSlice {
-                positions: vec![2, -7, 8, -1],
-                velocities: vec![0; 4],
-            },
+            axes: vec![
+                StateSlice {
+                    positions: vec![-1, 2, 4, 3],
+                    velocities: vec![0; 4],
+                },
+                StateSlice {
+                    positions: vec![0, -10, -8, 5],
+                    velocities: vec![0; 4],
+                },
+                StateSlice {
+                    positions: vec![2, -7, 8, -1],
+                    velocities: vec![0; 4],
+                },
+            ],
         });
         // Step 1
         sim.step();
         assert_eq!(
             sim.state(),
             &State {
-                x: StateSlice {
-                    positions: vec![2, 3, 1, 2],
-                    velocities: vec![3, 1, -3, -1],
-                },
-                y: StateSlice {
-                    positions: vec![-1, -7, -7, 2],
-                    velocities: vec![-1, 3, 1, -3],
-                },
-                z: StateSlice {
-                    positions: vec![1, -4, 5, 0],
-                    velocities: vec![-1, 3, -3, 1],
-                },
+                axes: vec![
+                    StateSlice {
+                        positions: vec![2, 3, 1, 2],
+                        velocities: vec![3, 1, -3, -1],
+                    },
+                    StateSlice {
+                        positions: vec![-1, -7, -7, 2],
+                        velocities: vec![-1, 3, 1, -3],
+                    },
+                    StateSlice {
+                        positions: vec![1, -4, 5, 0],
+                        velocities: vec![-1, 3, -3, 1],
+                    },
+                ],
             }
         );
         for _ in 0..9 {
             sim.step();
         }
+        assert_eq!(sim.state().potential_energy(), vec![6, 9, 10, 6]);
+        assert_eq!(sim.state().kinetic_energy(), vec![6, 5, 8, 3]);
         assert_eq!(sim.state().energy(), 179);
-        let i = sim.find_period();
+        let i = sim.find_period().unwrap();
         assert_eq!(i, 2772);
     }
 
     #[test]
     fn example2() {
-        let mut sim = Simulator::new(State {
-            x: StateSlice {
-                positions: vec![-8, 5, 2, 9],
-                velocities: vec![0; 4],
-            },
-            y: StateSlice {
-                positions: vec![-10, 5, -7, -8],
-                velocities: vec![0; 4],
-            },
-            z: StateSlice {
-                positions: vec![0, 10, 3, -3],
-                velocities: vec![0; 4],
-            },
-        });
+        let mut sim = Simulator::new(State::from_positions(vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ]));
         for _ in 0..100 {
             sim.step();
         }
         assert_eq!(sim.state().energy(), 1940);
-        let i = sim.find_period();
+        let i = sim.find_period().unwrap();
         assert_eq!(i, 4_686_774_924);
     }
 
-    fn read_input(data: &str) -> State<i32> {
-        let re = Regex::new(r"<x=((?:-)?\d+), y=((?:-)?\d+), z=((?:-)?\d+)>").unwrap();
-        let data: Vec<_> = data
-            .lines()
-            .map(|line| {
-                let caps = re.captures(line).unwrap();
-                assert_eq!(caps.len(), 4);
-                [
-                    caps[1].parse().unwrap(),
-                    caps[2].parse().unwrap(),
-                    caps[3].parse().unwrap(),
-                ]
-            })
-            .collect();
-        State {
-            x: StateSlice {
-                positions: data.iter().map(|line| line[0]).collect(),
-                velocities: vec![0; data.len()],
-            },
-            y: StateSlice {
-                positions: data.iter().map(|line| line[1]).collect(),
-                velocities: vec![0; data.len()],
-            },
-            z: StateSlice {
-                positions: data.iter().map(|line| line[2]).collect(),
-                velocities: vec![0; data.len()],
-            },
+    #[test]
+    fn large_periods() {
+        assert_eq!(
+            checked_lcm(vec![1_000_000_007, 998_244_353, 1_000_000_009]).unwrap(),
+            998_244_368_971_909_710_889_394_239
+        );
+        assert!(checked_lcm(vec![u64::MAX, u64::MAX - 2]).is_ok());
+        match checked_lcm(vec![u64::MAX, u64::MAX - 1, u64::MAX - 2]) {
+            Err(PeriodError::Overflow) => (),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn step_n() {
+        let initial = State::from_positions(vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ]);
+        let mut expected = Simulator::new(initial.clone());
+        for _ in 0..100 {
+            expected.step();
+        }
+        let mut sim = Simulator::new(initial);
+        sim.step_n(100);
+        assert_eq!(sim.state(), expected.state());
+    }
+
+    #[test]
+    fn recording() {
+        let initial = State::from_positions(vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ]);
+        let mut sim = Simulator::new(initial.clone());
+        let energies = sim.run_recording(10);
+        assert_eq!(energies.len(), 10);
+        assert_eq!(energies.last(), Some(&sim.state().energy()));
+        assert_eq!(energies[9], 179);
+        let mut expected = Simulator::new(initial);
+        for energy in energies {
+            expected.step();
+            assert_eq!(expected.state().energy(), energy);
+        }
+    }
+
+    #[test]
+    fn one_shot_energy() {
+        let example1 = State::from_positions(vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ]);
+        assert_eq!(energy_after(&example1, 10), 179);
+        assert_eq!(energy_after(&example1, 0), 0);
+        let example2 = State::from_positions(vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ]);
+        let initial = example2.clone();
+        assert_eq!(energy_after(&example2, 100), 1940);
+        assert_eq!(example2, initial);
+    }
+
+    #[test]
+    fn two_dimensions() {
+        let mut sim = Simulator::new(State::from_positions(vec![vec![0, 0], vec![2, 1]]));
+        sim.step();
+        assert_eq!(
+            sim.state(),
+            &State {
+                axes: vec![
+                    StateSlice {
+                        positions: vec![1, 1],
+                        velocities: vec![1, -1],
+                    },
+                    StateSlice {
+                        positions: vec![1, 0],
+                        velocities: vec![1, -1],
+                    },
+                ],
+            }
+        );
+        assert_eq!(sim.state().energy(), 6);
+        assert_eq!(sim.find_period().unwrap(), 12);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_state("<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>").unwrap(),
+            State::from_positions(vec![vec![-1, 0, 2], vec![2, -10, -7]])
+        );
+        match parse_state("<x=-1, y=0, z=2>\n<x=2, y=-10>") {
+            Err(ParseError::LineInvalid { line, text }) => {
+                assert_eq!(line, 2);
+                assert_eq!(text, "<x=2, y=-10>");
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        match parse_state("<x=99999999999999999999, y=0, z=0>") {
+            Err(ParseError::CoordinateInvalid { line, .. }) => assert_eq!(line, 1),
+            other => panic!("Unexpected result {:?}", other),
         }
     }
 
     #[test]
     fn day_12_part_1() {
-        let mut sim = Simulator::new(read_input(include_str!("input")));
+        let mut sim = Simulator::new(parse_state(include_str!("input")).unwrap());
         println!("{:?}", sim.state());
-        for _ in 0..1000 {
-            sim.step();
-        }
+        sim.step_n(1000);
         assert_eq!(sim.state().energy(), 7202);
     }
 
     #[test]
     fn day_12_part_2() {
-        let sim = Simulator::new(read_input(include_str!("input")));
-        let i = sim.find_period();
+        let sim = Simulator::new(parse_state(include_str!("input")).unwrap());
+        let i = sim.find_period().unwrap();
         assert_eq!(i, 537_881_600_740_876);
     }
 }
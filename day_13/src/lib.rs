@@ -1,6 +1,18 @@
 use day_9::computer::Computer;
-use std::cell::RefCell;
+use snafu::Snafu;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use util::grid::Canvas;
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum GameError {
+    #[snafu(display("Ran out of joystick moves after {}", moves))]
+    MovesExhausted { moves: usize },
+
+    #[snafu(display("Invalid tile {}", value))]
+    BadTile { value: isize },
+}
 
 pub type Coord2D = (isize, isize);
 
@@ -12,6 +24,39 @@ pub enum Tile {
     Ball,
 }
 
+impl TryFrom<isize> for Tile {
+    type Error = GameError;
+
+    fn try_from(value: isize) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Tile::Wall),
+            2 => Ok(Tile::Block),
+            3 => Ok(Tile::HorizontalPaddle),
+            4 => Ok(Tile::Ball),
+            _ => Err(GameError::BadTile { value }),
+        }
+    }
+}
+
+impl Tile {
+    /// The tile drawn by `id`, `None` for an empty cell.
+    pub fn from_id(id: isize) -> Result<Option<Self>, GameError> {
+        match id {
+            0 => Ok(None),
+            _ => Tile::try_from(id).map(Some),
+        }
+    }
+
+    fn id(&self) -> isize {
+        match self {
+            Tile::Wall => 1,
+            Tile::Block => 2,
+            Tile::HorizontalPaddle => 3,
+            Tile::Ball => 4,
+        }
+    }
+}
+
 enum NextInput {
     X,
     Y,
@@ -21,6 +66,8 @@ enum NextInput {
 pub struct GameFsm {
     pub tiles: HashMap<Coord2D, Tile>,
     pub score: Option<isize>,
+    ball: Option<Coord2D>,
+    paddle: Option<Coord2D>,
     next_input: NextInput,
     x: Option<isize>,
     y: Option<isize>,
@@ -31,13 +78,73 @@ impl GameFsm {
         GameFsm {
             tiles: HashMap::new(),
             score: None,
+            ball: None,
+            paddle: None,
             next_input: NextInput::X,
             x: None,
             y: None,
         }
     }
 
-    fn input(&mut self, v: isize) {
+    /// Replay a recorded output stream of the cabinet.
+    pub fn from_outputs(outputs: &[isize]) -> Result<Self, GameError> {
+        let mut fsm = GameFsm::new();
+        for v in outputs {
+            fsm.try_input(*v)?;
+        }
+        Ok(fsm)
+    }
+
+    /// The output stream that draws the current tiles, followed by the score.
+    pub fn to_outputs(&self) -> Vec<isize> {
+        let mut tiles: Vec<_> = self.tiles.iter().collect();
+        tiles.sort_by_key(|((x, y), _)| (*y, *x));
+        let mut outputs: Vec<_> = tiles
+            .into_iter()
+            .flat_map(|((x, y), tile)| vec![*x, *y, tile.id()])
+            .collect();
+        if let Some(score) = self.score {
+            outputs.extend(&[-1, 0, score]);
+        }
+        outputs
+    }
+
+    pub fn ball(&self) -> Option<Coord2D> {
+        self.ball
+    }
+
+    pub fn paddle(&self) -> Option<Coord2D> {
+        self.paddle
+    }
+
+    pub fn count(&self, tile: &Tile) -> usize {
+        self.tiles.values().filter(|t| *t == tile).count()
+    }
+
+    /// Draw the board over the bounding box of its tiles.
+    pub fn render(&self) -> String {
+        let mut canvas = Canvas::new();
+        for ((x, y), tile) in &self.tiles {
+            let ch = match tile {
+                Tile::Wall => '#',
+                Tile::Block => '*',
+                Tile::HorizontalPaddle => '_',
+                Tile::Ball => 'o',
+            };
+            canvas.set(*x, *y, ch);
+        }
+        canvas.to_string()
+    }
+
+    /// Same as `try_input`, panicking on an invalid tile.
+    pub fn input(&mut self, v: isize) {
+        self.try_input(v).unwrap()
+    }
+
+    /// Feed one value of the cabinet's output stream.
+    ///
+    /// On an invalid tile the whole instruction is discarded.
+    pub fn try_input(&mut self, v: isize) -> Result<(), GameError> {
         match self.next_input {
             NextInput::X => {
                 self.x = Some(v);
@@ -48,19 +155,25 @@ impl GameFsm {
                 self.next_input = NextInput::Tile;
             }
             NextInput::Tile => {
-                match (self.x.unwrap(), self.y.unwrap()) {
+                let (x, y) = (self.x.take().unwrap(), self.y.take().unwrap());
+                self.next_input = NextInput::X;
+                match (x, y) {
                     (-1, 0) => {
                         self.score = Some(v);
                     }
                     (x, y) => {
-                        let tile = match v {
-                            0 => None,
-                            1 => Some(Tile::Wall),
-                            2 => Some(Tile::Block),
-                            3 => Some(Tile::HorizontalPaddle),
-                            4 => Some(Tile::Ball),
-                            _ => unreachable!(),
-                        };
+                        let tile = Tile::from_id(v)?;
+                        if self.ball == Some((x, y)) {
+                            self.ball = None;
+                        }
+                        if self.paddle == Some((x, y)) {
+                            self.paddle = None;
+                        }
+                        match tile {
+                            Some(Tile::Ball) => self.ball = Some((x, y)),
+                            Some(Tile::HorizontalPaddle) => self.paddle = Some((x, y)),
+                            _ => (),
+                        }
                         if let Some(tile) = tile {
                             self.tiles.insert((x, y), tile);
                         } else {
@@ -68,46 +181,91 @@ impl GameFsm {
                         }
                     }
                 }
-                self.x = None;
-                self.y = None;
-                self.next_input = NextInput::X;
             }
         }
+        Ok(())
+    }
+}
+
+/// Move the paddle towards the ball.
+pub fn follow_ball(fsm: &GameFsm) -> isize {
+    let (paddle, _) = fsm.paddle().unwrap();
+    let (ball, _) = fsm.ball().unwrap();
+    (ball - paddle).signum()
+}
+
+pub fn run_arcade_cabinet(intcode: Vec<isize>) -> Result<GameFsm, GameError> {
+    run_arcade_cabinet_with(intcode, follow_ball)
+}
+
+/// Play the game, moving the joystick as told by `strategy`.
+///
+/// Stops at the first invalid tile drawn by the cabinet.
+pub fn run_arcade_cabinet_with<F>(
+    intcode: Vec<isize>,
+    mut strategy: F,
+) -> Result<GameFsm, GameError>
+where
+    F: FnMut(&GameFsm) -> isize,
+{
+    let fsm = RefCell::new(GameFsm::new());
+    let error = Cell::new(None);
+    let mut computer = Computer::new(
+        intcode,
+        || strategy(&fsm.borrow()),
+        |v| {
+            if let Err(e) = fsm.borrow_mut().try_input(v) {
+                error.set(Some(e));
+            }
+        },
+    );
+    while !computer.halted() {
+        computer.run_one().unwrap();
+        if let Some(e) = error.take() {
+            return Err(e);
+        }
     }
+    drop(computer);
+    Ok(fsm.into_inner())
 }
 
-pub fn run_arcade_cabinet(intcode: Vec<isize>) -> GameFsm {
+/// Play the game feeding a prerecorded sequence of joystick `moves`.
+pub fn run_arcade_cabinet_with_inputs(
+    intcode: Vec<isize>,
+    moves: &[isize],
+) -> Result<GameFsm, GameError> {
     let fsm = RefCell::new(GameFsm::new());
-    Computer::new(
+    let used = Cell::new(0);
+    let error = Cell::new(None);
+    let mut computer = Computer::new(
         intcode,
         || {
-            let fsm = fsm.borrow();
-            let paddle = fsm
-                .tiles
-                .iter()
-                .filter(|(_, tile)| **tile == Tile::HorizontalPaddle)
-                .map(|((x, _), _)| x)
-                .next()
-                .unwrap();
-            let ball = fsm
-                .tiles
-                .iter()
-                .filter(|(_, tile)| **tile == Tile::Ball)
-                .map(|((x, _), _)| x)
-                .next()
-                .unwrap();
-            (ball - paddle).signum()
+            used.set(used.get() + 1);
+            moves[used.get() - 1]
+        },
+        |v| {
+            if let Err(e) = fsm.borrow_mut().try_input(v) {
+                error.set(Some(e));
+            }
         },
-        |v| fsm.borrow_mut().input(v),
-    )
-    .run()
-    .unwrap();
-    fsm.into_inner()
+    );
+    while !computer.halted() {
+        if computer.awaiting_input() && used.get() == moves.len() {
+            return Err(GameError::MovesExhausted { moves: moves.len() });
+        }
+        computer.run_one().unwrap();
+        if let Some(e) = error.take() {
+            return Err(e);
+        }
+    }
+    drop(computer);
+    Ok(fsm.into_inner())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use day_9::parse_intcode;
 
     #[test]
     fn example_1() {
@@ -118,27 +276,87 @@ mod tests {
         assert_eq!(fsm.tiles.len(), 2);
         assert_eq!(fsm.tiles.get(&(1, 2)), Some(&Tile::HorizontalPaddle));
         assert_eq!(fsm.tiles.get(&(6, 5)), Some(&Tile::Ball));
+        assert_eq!(fsm.count(&Tile::Ball), 1);
+        assert_eq!(fsm.ball(), Some((6, 5)));
+        assert_eq!(fsm.paddle(), Some((1, 2)));
+        assert_eq!(fsm.count(&Tile::Wall), 0);
+        assert_eq!(
+            fsm.render(),
+            "_     \n\
+             \x20     \n\
+             \x20     \n\
+             \x20    o"
+        );
     }
 
     fn read_intcode(data: &str) -> Vec<isize> {
-        data.lines()
-            .next()
-            .unwrap()
-            .split(',')
-            .map(|x| x.parse())
-            .collect::<Result<_, _>>()
-            .unwrap()
+        parse_intcode(data).unwrap()
+    }
+
+    #[test]
+    fn replay() {
+        let fsm = GameFsm::from_outputs(&[6, 5, 4, 1, 2, 3, -1, 0, 7, 0, 0, 1, 6, 5, 0, 6, 6, 4])
+            .unwrap();
+        assert_eq!(fsm.ball(), Some((6, 6)));
+        assert_eq!(fsm.paddle(), Some((1, 2)));
+        assert_eq!(fsm.score, Some(7));
+        let outputs = fsm.to_outputs();
+        assert_eq!(outputs, vec![0, 0, 1, 1, 2, 3, 6, 6, 4, -1, 0, 7]);
+        let replayed = GameFsm::from_outputs(&outputs).unwrap();
+        assert_eq!(replayed.tiles, fsm.tiles);
+        assert_eq!(replayed.score, fsm.score);
+        assert_eq!(replayed.ball(), fsm.ball());
+        assert_eq!(replayed.paddle(), fsm.paddle());
     }
 
     #[test]
     fn day_13_part_1() {
-        let fsm = run_arcade_cabinet(read_intcode(include_str!("input")));
+        let fsm = run_arcade_cabinet(read_intcode(include_str!("input"))).unwrap();
+        assert_eq!(fsm.count(&Tile::Block), 280);
+    }
+
+    #[test]
+    fn moving_ball() {
+        let mut fsm = GameFsm::new();
+        for i in &[6, 5, 4, 7, 6, 4, 6, 5, 0] {
+            fsm.input(*i);
+        }
+        assert_eq!(fsm.ball(), Some((7, 6)));
+        for i in &[7, 6, 1] {
+            fsm.input(*i);
+        }
+        assert_eq!(fsm.ball(), None);
+        assert_eq!(fsm.paddle(), None);
+    }
+
+    #[test]
+    fn custom_strategy() {
+        let fsm = run_arcade_cabinet_with(read_intcode(include_str!("input")), |_| 0).unwrap();
+        assert_eq!(fsm.count(&Tile::Block), 280);
+    }
+
+    #[test]
+    fn bad_tile() {
+        let mut fsm = GameFsm::new();
+        fsm.try_input(1).unwrap();
+        fsm.try_input(2).unwrap();
+        assert_eq!(fsm.try_input(9), Err(GameError::BadTile { value: 9 }));
+        assert!(fsm.tiles.is_empty());
+        // The stream starts over with the next instruction.
+        for v in &[3, 4, 2] {
+            fsm.try_input(*v).unwrap();
+        }
+        assert_eq!(fsm.tiles.get(&(3, 4)), Some(&Tile::Block));
+        assert_eq!(Tile::from_id(0), Ok(None));
+        assert_eq!(Tile::try_from(4), Ok(Tile::Ball));
         assert_eq!(
-            fsm.tiles
-                .iter()
-                .filter(|(_, tile)| **tile == Tile::Block)
-                .count(),
-            280
+            GameFsm::from_outputs(&[1, 2, 9]).err(),
+            Some(GameError::BadTile { value: 9 })
+        );
+        // Draw a tile with id 7 at (0, 0), then halt.
+        assert_eq!(
+            run_arcade_cabinet_with(vec![104, 0, 104, 0, 104, 7, 99], |_| 0).err(),
+            Some(GameError::BadTile { value: 7 })
         );
     }
 
@@ -156,14 +374,27 @@ mod tests {
     fn day_13_part_2() {
         let mut intcode = read_intcode(include_str!("input"));
         intcode[0] = 2;
-        let fsm = run_arcade_cabinet(intcode);
+        let fsm = run_arcade_cabinet(intcode).unwrap();
+        assert_eq!(fsm.count(&Tile::Block), 0);
+        assert_eq!(fsm.score, Some(13298));
+    }
+
+    #[test]
+    fn transcript() {
+        let mut intcode = read_intcode(include_str!("input"));
+        intcode[0] = 2;
+        let mut moves = vec![];
+        run_arcade_cabinet_with(intcode.clone(), |fsm| {
+            let m = follow_ball(fsm);
+            moves.push(m);
+            m
+        })
+        .unwrap();
+        let fsm = run_arcade_cabinet_with_inputs(intcode.clone(), &moves).unwrap();
+        assert_eq!(fsm.score, Some(13298));
         assert_eq!(
-            fsm.tiles
-                .iter()
-                .filter(|(_, tile)| **tile == Tile::Block)
-                .count(),
-            0
+            run_arcade_cabinet_with_inputs(intcode, &moves[..10]).err(),
+            Some(GameError::MovesExhausted { moves: 10 })
         );
-        assert_eq!(fsm.score, Some(13298));
     }
 }
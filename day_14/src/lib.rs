@@ -1,10 +1,11 @@
+use snafu::Snafu;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::hash::BuildHasher;
 use std::hash::Hash;
-use std::ops::AddAssign;
 use std::str::FromStr;
+use util::AddOrInsert;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Chemical {
@@ -31,37 +32,84 @@ pub struct Reaction {
     pub output: usize,
 }
 
-trait AddOrInsert<K, V> {
-    fn add_or_insert(&mut self, k: K, v: V);
+/// Chemicals that need each other to be produced, each one needing the next
+/// and the last one needing the first.
+#[derive(Debug, PartialEq, Snafu)]
+#[snafu(display("Cyclic reactions between {:?}", chemicals))]
+pub struct CycleError {
+    pub chemicals: Vec<Chemical>,
 }
 
-impl<K, V, S> AddOrInsert<K, V> for HashMap<K, V, S>
-where
-    K: Eq + Hash,
-    V: AddAssign + Default,
-    S: BuildHasher,
-{
-    fn add_or_insert(self: &mut Self, k: K, v: V) {
-        *self.entry(k).or_default() += v;
+/// Check that no chemical needs itself to be produced, as `solve_for` assumes.
+pub fn validate<S: BuildHasher>(
+    reactions: &HashMap<Chemical, Reaction, S>,
+) -> Result<(), CycleError> {
+    let mut done = HashSet::new();
+    for chemical in reactions.keys() {
+        visit(reactions, chemical, &mut vec![], &mut done)?;
+    }
+    Ok(())
+}
+
+/// Depth-first search from `chemical`, failing if it is already on `path`.
+fn visit<'a, S: BuildHasher>(
+    reactions: &'a HashMap<Chemical, Reaction, S>,
+    chemical: &'a Chemical,
+    path: &mut Vec<&'a Chemical>,
+    done: &mut HashSet<&'a Chemical>,
+) -> Result<(), CycleError> {
+    if done.contains(chemical) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|c| *c == chemical) {
+        return Err(CycleError {
+            chemicals: path[start..].iter().map(|c| (*c).clone()).collect(),
+        });
     }
+    if let Some(reaction) = reactions.get(chemical) {
+        path.push(chemical);
+        for input in reaction.inputs.keys() {
+            visit(reactions, input, path, done)?;
+        }
+        path.pop();
+    }
+    done.insert(chemical);
+    Ok(())
+}
+
+/// The base chemicals needed and the chemicals left over.
+pub type Solution<S> = (HashMap<Chemical, usize>, HashMap<Chemical, usize, S>);
+
+/// Same as `solve_for`, first checking that the reactions are acyclic.
+pub fn try_solve_for<S1: BuildHasher, S2: BuildHasher>(
+    reactions: &HashMap<Chemical, Reaction, S1>,
+    chemical: &Chemical,
+    quantity: usize,
+    leftovers: HashMap<Chemical, usize, S2>,
+) -> Result<Solution<S2>, CycleError> {
+    validate(reactions)?;
+    Ok(solve_for(reactions, chemical, quantity, leftovers))
 }
 
+/// The base chemicals needed to produce `quantity` of `chemical`.
+///
+/// The reactions must be acyclic, see `validate` and `try_solve_for`.
 pub fn solve_for<S1: BuildHasher, S2: BuildHasher>(
     reactions: &HashMap<Chemical, Reaction, S1>,
     chemical: &Chemical,
     quantity: usize,
     mut leftovers: HashMap<Chemical, usize, S2>,
-) -> (HashMap<Chemical, usize>, HashMap<Chemical, usize, S2>) {
+) -> Solution<S2> {
     if quantity == 0 {
         return (HashMap::new(), leftovers);
     }
     let already_available = *leftovers.get(chemical).unwrap_or(&0);
     if already_available >= quantity {
         // Use part of the leftovers and don't run any reaction.
-        *leftovers.get_mut(&chemical).unwrap() -= quantity;
+        *leftovers.get_mut(chemical).unwrap() -= quantity;
         return (HashMap::new(), leftovers);
     }
-    leftovers.remove(&chemical); // We are going to consume all the leftovers.
+    leftovers.remove(chemical); // We are going to consume all the leftovers.
     let quantity = quantity - already_available;
     let reaction = &reactions[chemical];
     let ratio = (reaction.output + quantity - 1) / reaction.output;
@@ -111,7 +159,7 @@ pub fn optimize_ore_to_fuel<S: BuildHasher>(
         }
     }
     while lo + 1 != hi {
-        let mid = (lo + hi + 1) / 2;
+        let mid = (lo + hi).div_ceil(2);
         let used = get_ores(mid);
         match used.cmp(&ores) {
             Ordering::Equal => return mid,
@@ -122,6 +170,20 @@ pub fn optimize_ore_to_fuel<S: BuildHasher>(
     lo
 }
 
+/// Same as `optimize_ore_to_fuel`, also returning the ore consumed and the
+/// leftover chemicals when producing that much fuel.
+///
+/// Fails without solving anything if the reactions are cyclic.
+pub fn max_fuel_detailed<S: BuildHasher>(
+    reactions: &HashMap<Chemical, Reaction, S>,
+    ores: usize,
+) -> Result<(usize, usize, HashMap<Chemical, usize>), CycleError> {
+    validate(reactions)?;
+    let fuel = optimize_ore_to_fuel(reactions, ores);
+    let (base, leftovers) = solve_for(reactions, &Chemical::Fuel, fuel, HashMap::new());
+    Ok((fuel, base[&Chemical::Ore], leftovers))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -152,6 +214,46 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn cycle() {
+        let reactions = read_input(
+            "1 ORE, 1 B => 1 A\n\
+             1 A => 1 B\n\
+             1 A => 1 FUEL",
+        );
+        let mut chemicals = validate(&reactions).unwrap_err().chemicals;
+        chemicals.sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(
+            chemicals,
+            vec![
+                Chemical::Other("A".to_owned()),
+                Chemical::Other("B".to_owned())
+            ]
+        );
+        let reactions = read_input("1 FUEL => 1 FUEL");
+        assert_eq!(
+            validate(&reactions),
+            Err(CycleError {
+                chemicals: vec![Chemical::Fuel]
+            })
+        );
+        assert_eq!(
+            try_solve_for(&reactions, &Chemical::Fuel, 1, HashMap::new()),
+            Err(CycleError {
+                chemicals: vec![Chemical::Fuel]
+            })
+        );
+        assert!(max_fuel_detailed(&reactions, 1_000_000_000_000).is_err());
+        assert_eq!(
+            CycleError {
+                chemicals: vec![Chemical::Fuel]
+            }
+            .to_string(),
+            "Cyclic reactions between [Fuel]"
+        );
+        assert_eq!(validate(&read_input(include_str!("input"))), Ok(()));
+    }
+
     #[test]
     fn example_1() {
         let reactions = read_input(
@@ -214,6 +316,18 @@ mod tests {
             optimize_ore_to_fuel(&reactions, 1_000_000_000_000),
             82_892_753
         );
+        let (fuel, ore, leftovers) = max_fuel_detailed(&reactions, 1_000_000_000_000).unwrap();
+        assert_eq!(fuel, 82_892_753);
+        assert!(ore <= 1_000_000_000_000);
+        assert_eq!(
+            solve_for(&reactions, &Chemical::Fuel, fuel, HashMap::new()),
+            (
+                [(Chemical::Ore, ore)].iter().cloned().collect(),
+                leftovers.clone()
+            )
+        );
+        let (more, _) = solve_for(&reactions, &Chemical::Fuel, 1, leftovers);
+        assert!(ore + more[&Chemical::Ore] > 1_000_000_000_000);
     }
 
     #[test]
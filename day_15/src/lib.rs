@@ -1,50 +1,53 @@
 use day_9::computer::Computer;
+use snafu::{OptionExt, Snafu};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::hash::Hash;
-use std::slice::Iter;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Coordinates(isize, isize);
-
-impl Coordinates {
-    pub fn neighbor(self, dir: Direction) -> Self {
-        match dir {
-            Direction::North => Coordinates(self.0 + 1, self.1),
-            Direction::South => Coordinates(self.0 - 1, self.1),
-            Direction::West => Coordinates(self.0, self.1 - 1),
-            Direction::East => Coordinates(self.0, self.1 + 1),
-        }
+pub use util::grid::{Coord, Direction};
+use util::{AdjInsert, AdjList};
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum DroidError {
+    #[snafu(display("Invalid droid status {}", status))]
+    StatusInvalid { status: isize },
+
+    #[snafu(display("The droid didn't reach the oxygen system"))]
+    OxygenMissing,
+}
+
+/// The droid's movement command for a direction.
+fn command(dir: Direction) -> isize {
+    match dir {
+        Direction::North => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+        Direction::East => 4,
     }
 }
 
+/// The droid's reply to a movement command.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(isize)]
-pub enum Direction {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
+pub enum StatusCode {
+    /// Hit a wall, the position didn't change.
+    Wall,
+    /// Moved one step.
+    Moved,
+    /// Moved one step, reaching the oxygen system.
+    Oxygen,
 }
-impl Direction {
-    pub fn iter() -> Iter<'static, Direction> {
-        static DIRECTIONS: [Direction; 4] = [
-            Direction::North,
-            Direction::South,
-            Direction::West,
-            Direction::East,
-        ];
-        DIRECTIONS.iter()
-    }
-
-    pub fn opposite(self) -> Self {
-        match self {
-            Direction::North => Direction::South,
-            Direction::South => Direction::North,
-            Direction::West => Direction::East,
-            Direction::East => Direction::West,
+
+impl TryFrom<isize> for StatusCode {
+    type Error = DroidError;
+
+    fn try_from(status: isize) -> Result<Self, Self::Error> {
+        match status {
+            0 => Ok(StatusCode::Wall),
+            1 => Ok(StatusCode::Moved),
+            2 => Ok(StatusCode::Oxygen),
+            _ => Err(DroidError::StatusInvalid { status }),
         }
     }
 }
@@ -57,22 +60,23 @@ pub enum Exploration {
 
 type Path = Vec<Direction>;
 
+/// A position to explore, along with the path that reaches it from the origin.
 #[derive(Debug)]
-struct Target(Coordinates, Path);
+pub struct Target(pub Coord, pub Path);
 
 pub struct Explorer {
     dir_queue: Path,
     target: Target,
     to_visit: VecDeque<Target>,
-    visited: HashSet<Coordinates>,
+    visited: HashSet<Coord>,
 }
 
 impl Explorer {
     pub fn new() -> Self {
-        let base = Coordinates(0, 0);
+        let base = Coord::default();
         let mut to_visit: VecDeque<Target> = Direction::iter()
             .cloned()
-            .map(|dir| Target(base.neighbor(dir), vec![dir]))
+            .map(|dir| Target(base + dir, vec![dir]))
             .collect();
         let target = to_visit.pop_front().unwrap();
         Explorer {
@@ -111,7 +115,7 @@ impl Explorer {
             let path = &self.target.1;
             self.visited.insert(self.target.0);
             for dir in Direction::iter().cloned() {
-                let neighbor = pos.neighbor(dir);
+                let neighbor = *pos + dir;
                 if !self.visited.contains(&neighbor) {
                     let mut path = path.clone();
                     path.push(dir);
@@ -134,6 +138,16 @@ impl Explorer {
         self.target.1.clone()
     }
 
+    /// The targets still to be explored, in order.
+    pub fn frontier(&self) -> &VecDeque<Target> {
+        &self.to_visit
+    }
+
+    /// The positions explored so far, both spaces and walls.
+    pub fn visited(&self) -> &HashSet<Coord> {
+        &self.visited
+    }
+
     fn find_path(from: &Target, to: &Target) -> Path {
         let mut from = from.1.iter().peekable();
         let mut to = to.1.iter().peekable();
@@ -158,72 +172,92 @@ impl Default for Explorer {
     }
 }
 
-pub fn find_oxygen_system(intcode: Vec<isize>) -> Path {
+pub fn find_oxygen_system(intcode: Vec<isize>) -> Result<Path, DroidError> {
     struct State {
         explorer: Explorer,
         path: Option<Path>,
+        error: Option<DroidError>,
     }
     let state = RefCell::new(State {
         explorer: Explorer::new(),
         path: None,
+        error: None,
     });
     let mut computer = Computer::new(
         intcode,
-        || *state.borrow().explorer.next_direction() as isize,
+        || command(*state.borrow().explorer.next_direction()),
         |v| {
             let mut state = state.borrow_mut();
-            match v {
-                0 => {
+            match StatusCode::try_from(v) {
+                Ok(StatusCode::Wall) => {
                     let e = state.explorer.notify_wall();
                     assert_eq!(e, Exploration::InProgress)
                 }
-                1 => {
+                Ok(StatusCode::Moved) => {
                     let e = state.explorer.notify_space();
                     assert_eq!(e, Exploration::InProgress)
                 }
-                2 => state.path = Some(state.explorer.get_target_path()),
-                _ => panic!(),
+                Ok(StatusCode::Oxygen) => state.path = Some(state.explorer.get_target_path()),
+                Err(e) => state.error = Some(e),
             }
         },
     );
-    while state.borrow().path.is_none() {
+    while state.borrow().path.is_none() && state.borrow().error.is_none() {
         let ok = computer.run_one().unwrap();
         assert!(ok);
     }
-    state.into_inner().path.unwrap()
+    drop(computer);
+    let state = state.into_inner();
+    match state.error {
+        Some(e) => Err(e),
+        None => Ok(state.path.unwrap()),
+    }
 }
 
-type AdjList<T> = HashMap<T, HashSet<T>>;
-
-trait AdjInsert<T> {
-    fn adj_insert(&mut self, x: T, y: T);
-}
-impl<T> AdjInsert<T> for AdjList<T>
-where
-    T: Clone + Eq + Hash,
-{
-    fn adj_insert(&mut self, x: T, y: T) {
-        self.entry(x.clone()).or_default().insert(y.clone());
-        self.entry(y).or_default().insert(x);
-    }
+/// Number of steps from the origin to the oxygen system.
+///
+/// The explorer visits cells in breadth-first order, but moving between targets
+/// can take detours: the distance is therefore computed on the complete map.
+pub fn distance_to_oxygen(intcode: Vec<isize>) -> Result<usize, DroidError> {
+    let (center, map, _, _) = build_map(intcode)?;
+    shortest_distance(Coord::default(), &center.context(OxygenMissing)?, &map)
+        .context(OxygenMissing)
 }
 
-pub fn build_map(intcode: Vec<isize>) -> (Coordinates, AdjList<Coordinates>) {
+/// The oxygen system, if found, the map, the unreached cells and the walls.
+pub type Survey = (
+    Option<Coord>,
+    AdjList<Coord>,
+    HashSet<Coord>,
+    HashSet<Coord>,
+);
+
+/// Explore the whole area, returning the oxygen system, the map, the cells that
+/// were queued for exploration but never reached and the walls the droid hit.
+///
+/// The set of unreached cells is empty unless the program stopped before the
+/// exploration was finished, in which case the oxygen system may be missing
+/// too.
+pub fn build_map(intcode: Vec<isize>) -> Result<Survey, DroidError> {
     struct State {
-        pos: Coordinates,
+        pos: Coord,
         dir: Option<Direction>,
         stop: bool,
-        center: Option<Coordinates>,
-        map: AdjList<Coordinates>,
+        center: Option<Coord>,
+        map: AdjList<Coord>,
+        walls: HashSet<Coord>,
         explorer: Explorer,
+        error: Option<DroidError>,
     }
     let state = RefCell::new(State {
-        pos: Coordinates(0, 0),
+        pos: Coord::default(),
         dir: None,
         stop: false,
         center: None,
         map: AdjList::new(),
+        walls: HashSet::new(),
         explorer: Explorer::new(),
+        error: None,
     });
     let mut computer = Computer::new(
         intcode,
@@ -231,21 +265,33 @@ pub fn build_map(intcode: Vec<isize>) -> (Coordinates, AdjList<Coordinates>) {
             let mut state = state.borrow_mut();
             let dir = *state.explorer.next_direction();
             state.dir = Some(dir);
-            dir as isize
+            command(dir)
         },
         |v| {
             let mut state = state.borrow_mut();
-            state.stop = match v {
-                0 => state.explorer.notify_wall(),
-                1 => {
-                    let new_pos = state.pos.neighbor(state.dir.unwrap());
+            let status = match StatusCode::try_from(v) {
+                Ok(status) => status,
+                Err(e) => {
+                    state.error = Some(e);
+                    state.stop = true;
+                    return;
+                }
+            };
+            state.stop = match status {
+                StatusCode::Wall => {
+                    let wall = state.pos + state.dir.unwrap();
+                    state.walls.insert(wall);
+                    state.explorer.notify_wall()
+                }
+                StatusCode::Moved => {
+                    let new_pos = state.pos + state.dir.unwrap();
                     let old_pos = state.pos;
                     state.map.adj_insert(old_pos, new_pos);
                     state.pos = new_pos;
                     state.explorer.notify_space()
                 }
-                2 => {
-                    let new_pos = state.pos.neighbor(state.dir.unwrap());
+                StatusCode::Oxygen => {
+                    let new_pos = state.pos + state.dir.unwrap();
                     let old_pos = state.pos;
                     state.map.adj_insert(old_pos, new_pos);
                     state.pos = new_pos;
@@ -256,63 +302,164 @@ pub fn build_map(intcode: Vec<isize>) -> (Coordinates, AdjList<Coordinates>) {
                     }
                     state.explorer.notify_space()
                 }
-                _ => panic!(),
             } == Exploration::Finished;
         },
     );
     while !state.borrow().stop {
-        let ok = computer.run_one().unwrap();
-        assert!(ok);
+        if !computer.run_one().unwrap() {
+            break;
+        }
     }
+    drop(computer);
     let state = state.into_inner();
-    (state.center.unwrap(), state.map)
+    if let Some(e) = state.error {
+        return Err(e);
+    }
+    let unreached = state
+        .explorer
+        .frontier()
+        .iter()
+        .map(|target| target.0)
+        .filter(|pos| !state.explorer.visited().contains(pos))
+        .collect();
+    Ok((state.center, state.map, unreached, state.walls))
 }
 
-pub fn longest_distance<T>(center: T, map: &AdjList<T>) -> usize
+/// Draw the explored area with north at the top.
+///
+/// Only the `walls` the droid hit are drawn as walls, cells that were never
+/// explored are left blank.
+pub fn render_map(center: Coord, map: &AdjList<Coord>, walls: &HashSet<Coord>) -> String {
+    let origin = Coord::default();
+    let landmarks = [origin, center];
+    let all = || map.keys().chain(walls.iter()).chain(landmarks.iter());
+    let min_x = all().map(|pos| pos.x).min().unwrap();
+    let max_x = all().map(|pos| pos.x).max().unwrap();
+    let min_y = all().map(|pos| pos.y).min().unwrap();
+    let max_y = all().map(|pos| pos.y).max().unwrap();
+    (min_y..=max_y)
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| {
+                    let pos = Coord { x, y };
+                    if pos == center {
+                        'O'
+                    } else if pos == origin {
+                        'D'
+                    } else if map.contains_key(&pos) {
+                        '.'
+                    } else if walls.contains(&pos) {
+                        '#'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Minutes needed by the oxygen to reach each cell, spreading from `center`.
+pub fn fill_times<T>(center: T, map: &AdjList<T>) -> HashMap<T, usize>
 where
-    T: Eq + Hash,
+    T: Clone + Eq + Hash,
 {
-    let mut visited: HashSet<&T> = HashSet::new();
+    let mut times = HashMap::new();
     let mut to_visit: HashSet<&T> = [&center].iter().cloned().collect();
     for turn in 0.. {
-        visited.extend(to_visit.iter());
-        to_visit = map
+        if to_visit.is_empty() {
+            break;
+        }
+        times.extend(to_visit.iter().map(|c| ((*c).clone(), turn)));
+        to_visit = to_visit
             .iter()
-            .filter_map(|(c, adj)| {
-                if to_visit.contains(c) {
-                    Some(adj)
-                } else {
-                    None
-                }
-            })
+            .filter_map(|c| map.get(c))
             .flatten()
-            .filter(|c| !visited.contains(c))
+            .filter(|c| !times.contains_key(c))
             .collect();
-        if to_visit.is_empty() {
-            return turn;
-        }
     }
-    unreachable!()
+    times
+}
+
+/// The cells of the map that can't be reached from `from`.
+pub fn unreachable<T>(from: T, map: &AdjList<T>) -> HashSet<T>
+where
+    T: Clone + Eq + Hash,
+{
+    let times = fill_times(from, map);
+    map.keys()
+        .filter(|c| !times.contains_key(c))
+        .cloned()
+        .collect()
+}
+
+pub fn longest_distance<T>(center: T, map: &AdjList<T>) -> usize
+where
+    T: Clone + Eq + Hash,
+{
+    *fill_times(center, map).values().max().unwrap()
+}
+
+pub fn shortest_distance<T>(from: T, to: &T, map: &AdjList<T>) -> Option<usize>
+where
+    T: Clone + Eq + Hash,
+{
+    fill_times(from, map).get(to).copied()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use day_9::parse_intcode;
 
     fn read_intcode(data: &str) -> Vec<isize> {
-        data.lines()
-            .next()
-            .unwrap()
-            .split(',')
-            .map(|x| x.parse())
-            .collect::<Result<_, _>>()
-            .unwrap()
+        parse_intcode(data).unwrap()
     }
 
     #[test]
     fn day_15_part_1() {
-        let path = find_oxygen_system(read_intcode(include_str!("input")));
+        let path = find_oxygen_system(read_intcode(include_str!("input"))).unwrap();
         assert_eq!(path.len(), 270);
+        assert_eq!(
+            distance_to_oxygen(read_intcode(include_str!("input"))),
+            Ok(270)
+        );
+    }
+
+    #[test]
+    fn status_codes() {
+        assert_eq!(StatusCode::try_from(0), Ok(StatusCode::Wall));
+        assert_eq!(StatusCode::try_from(2), Ok(StatusCode::Oxygen));
+        assert_eq!(
+            StatusCode::try_from(3),
+            Err(DroidError::StatusInvalid { status: 3 })
+        );
+        // A droid that always replies with an unknown status.
+        let intcode = day_9::asm::assemble("loop: in 10\nout @7\njz @0, @loop").unwrap();
+        assert_eq!(
+            find_oxygen_system(intcode.clone()),
+            Err(DroidError::StatusInvalid { status: 7 })
+        );
+        assert_eq!(
+            build_map(intcode).unwrap_err(),
+            DroidError::StatusInvalid { status: 7 }
+        );
+    }
+
+    #[test]
+    fn explorer_progress() {
+        let mut explorer = Explorer::new();
+        assert_eq!(explorer.visited().len(), 1);
+        assert_eq!(explorer.frontier().len(), 3);
+        explorer.notify_space();
+        assert!(explorer.visited().contains(&Coord { x: 0, y: -1 }));
+        assert_eq!(explorer.frontier().len(), 5);
+        explorer.notify_space();
+        assert_eq!(explorer.visited().len(), 2);
+        explorer.notify_space();
+        assert!(explorer.visited().contains(&Coord { x: 1, y: 0 }));
+        assert_eq!(explorer.visited().len(), 3);
     }
 
     #[test]
@@ -334,6 +481,10 @@ mod tests {
         adj.adj_insert(1, 2);
         adj.adj_insert(1, 3);
         assert_eq!(longest_distance(0, &adj), 2);
+        assert_eq!(
+            fill_times(0, &adj),
+            [(0, 0), (1, 1), (2, 2), (3, 2)].iter().cloned().collect()
+        );
     }
 
     #[test]
@@ -345,10 +496,119 @@ mod tests {
         assert_eq!(longest_distance(0, &adj), 1);
     }
 
+    #[test]
+    fn disconnected() {
+        let mut adj = AdjList::new();
+        adj.adj_insert(0, 1);
+        adj.adj_insert(1, 2);
+        adj.adj_insert(3, 4);
+        assert_eq!(unreachable(0, &adj), [3, 4].iter().cloned().collect());
+        assert!(unreachable(1, &adj).contains(&4));
+        assert_eq!(unreachable(3, &adj).len(), 3);
+    }
+
+    #[test]
+    fn shortest() {
+        let mut adj = AdjList::new();
+        adj.adj_insert(0, 1);
+        adj.adj_insert(1, 2);
+        adj.adj_insert(2, 3);
+        adj.adj_insert(0, 3);
+        adj.adj_insert(4, 5);
+        assert_eq!(shortest_distance(0, &0, &adj), Some(0));
+        assert_eq!(shortest_distance(0, &2, &adj), Some(2));
+        assert_eq!(shortest_distance(0, &3, &adj), Some(1));
+        assert_eq!(shortest_distance(0, &5, &adj), None);
+    }
+
+    #[test]
+    fn droid_commands() {
+        let origin = Coord::default();
+        let moves: Vec<_> = Direction::iter()
+            .map(|dir| (command(*dir), origin + *dir))
+            .collect();
+        assert_eq!(
+            moves,
+            vec![
+                (1, Coord { x: 0, y: -1 }),
+                (4, Coord { x: 1, y: 0 }),
+                (2, Coord { x: 0, y: 1 }),
+                (3, Coord { x: -1, y: 0 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut adj = AdjList::new();
+        adj.adj_insert(Coord::default(), Coord { x: 0, y: -1 });
+        adj.adj_insert(Coord { x: 0, y: -1 }, Coord { x: 1, y: -1 });
+        // The cell south of the droid was never explored.
+        let walls = [(0, -2), (1, -2), (-1, -1), (2, -1), (-1, 0), (1, 0)]
+            .iter()
+            .map(|&(x, y)| Coord { x, y })
+            .collect();
+        assert_eq!(
+            render_map(Coord { x: 1, y: -1 }, &adj, &walls),
+            " ## \n\
+             #.O#\n\
+             #D# "
+        );
+    }
+
+    #[test]
+    fn walls() {
+        // Reply with the statuses appended after the program, in order.
+        let mut intcode = day_9::asm::assemble(
+            "
+                  arb @data
+            loop: in 1000
+                  out *0
+                  arb @1
+                  jz @0, @loop
+            data:
+            ",
+        )
+        .unwrap();
+        // A corridor from the origin to the oxygen system, east of it.
+        intcode.extend(&[0, 2, 1, 0, 0, 1, 0, 0, 0]);
+        let (center, map, unreached, walls) = build_map(intcode).unwrap();
+        assert_eq!(center, Some(Coord { x: 1, y: 0 }));
+        assert_eq!(map.len(), 2);
+        assert!(unreached.is_empty());
+        assert_eq!(
+            walls,
+            [(0, -1), (0, 1), (-1, 0), (1, -1), (2, 0), (1, 1)]
+                .iter()
+                .map(|&(x, y)| Coord { x, y })
+                .collect()
+        );
+    }
+
     #[test]
     fn day_15_part_2() {
-        let (center, map) = build_map(read_intcode(include_str!("input")));
-        assert_eq!(center, Coordinates(-18, -20));
-        assert_eq!(longest_distance(center, &map), 364);
+        let (center, map, unreached, walls) =
+            build_map(read_intcode(include_str!("input"))).unwrap();
+        assert_eq!(
+            walls,
+            map.keys()
+                .flat_map(|pos| pos.neighbors().to_vec())
+                .filter(|pos| !map.contains_key(pos))
+                .collect()
+        );
+        assert_eq!(center, Some(Coord { x: -20, y: 18 }));
+        assert!(unreached.is_empty());
+        assert_eq!(longest_distance(center.unwrap(), &map), 364);
+    }
+
+    #[test]
+    fn stopped_early() {
+        // Move north, then stop.
+        let intcode = day_9::asm::assemble("in 1000\nout @1\nhlt").unwrap();
+        let (center, map, unreached, _) = build_map(intcode.clone()).unwrap();
+        assert_eq!(center, None);
+        assert_eq!(map.len(), 2);
+        assert!(unreached.contains(&Coord { x: 0, y: -2 }));
+        assert_eq!(distance_to_oxygen(intcode), Err(DroidError::OxygenMissing));
     }
 }
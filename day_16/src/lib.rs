@@ -1,53 +1,128 @@
+use rayon::prelude::*;
+
+pub const BASE_PATTERN: [i32; 4] = [0, 1, 0, -1];
+
 fn fft_pattern<T>(base: &[T], digit: usize) -> impl Iterator<Item = &T> {
     base.iter()
-        .flat_map(move |d| std::iter::repeat(d).take(digit + 1))
+        .flat_map(move |d| std::iter::repeat_n(d, digit + 1))
         .cycle()
         .skip(1)
 }
 
+/// Accumulates in `i64`, so that long inputs or large patterns don't overflow.
+fn fft_digit(input: &[i32], pattern: &[i32], digit: usize) -> i32 {
+    (input
+        .iter()
+        .zip(fft_pattern(pattern, digit))
+        .map(|(x, y)| i64::from(*x) * i64::from(*y))
+        .sum::<i64>()
+        .abs()
+        % 10) as i32
+}
+
 pub fn fft(input: &[i32], pattern: &[i32]) -> Vec<i32> {
     (0..input.len())
-        .map(|digit| {
-            input
-                .iter()
-                .zip(fft_pattern(pattern, digit))
-                .map(|(x, y)| x * y)
-                .sum::<i32>()
-                .abs()
-                % 10
-        })
+        .map(|digit| fft_digit(input, pattern, digit))
         .collect()
 }
 
-/// Algorithm from [u/paul2718](https://www.reddit.com/r/adventofcode/comments/ebf5cy/2019_day_16_part_2_understanding_how_to_come_up/fb4bvw4/).
+/// Same as `fft`, for callers that want to be explicit about the 64-bit sums.
+pub fn fft64(input: &[i32], pattern: &[i32]) -> Vec<i32> {
+    fft(input, pattern)
+}
+
+/// Same as `fft`, computing the digits in parallel.
+pub fn fft_par(input: &[i32], pattern: &[i32]) -> Vec<i32> {
+    (0..input.len())
+        .into_par_iter()
+        .map(|digit| fft_digit(input, pattern, digit))
+        .collect()
+}
+
+/// Same as `fft`, writing the digits to `output` instead of a new vector.
+fn fft_into(input: &[i32], pattern: &[i32], output: &mut Vec<i32>) {
+    output.clear();
+    output.extend((0..input.len()).map(|digit| fft_digit(input, pattern, digit)));
+}
+
+/// Apply one phase of `fft` to `buf`, swapping it with `scratch`, so that
+/// reusing the same `scratch` across phases doesn't allocate.
+pub fn fft_in_place(buf: &mut Vec<i32>, pattern: &[i32], scratch: &mut Vec<i32>) {
+    fft_into(buf, pattern, scratch);
+    std::mem::swap(buf, scratch);
+}
+
+/// Apply `phases` phases of `fft` to `buf`, allocating a single scratch buffer.
+pub fn fft_n(buf: &mut Vec<i32>, pattern: &[i32], phases: usize) {
+    let mut scratch = Vec::with_capacity(buf.len());
+    for _ in 0..phases {
+        fft_in_place(buf, pattern, &mut scratch);
+    }
+}
+
+/// Apply `phases` phases of `fft` to the input.
+pub fn fft_phases(input: &[i32], pattern: &[i32], phases: usize) -> Vec<i32> {
+    let mut data = input.to_vec();
+    fft_n(&mut data, pattern, phases);
+    data
+}
+
 pub fn decode(input: &[i32]) -> i32 {
-    const REPS: usize = 10_000;
-    const PHASES: usize = 100;
+    decode_with(input, 100, 10_000)
+}
 
+/// Decode the message after `phases` phases of the input repeated `reps` times.
+pub fn decode_with(input: &[i32], phases: usize, reps: usize) -> i32 {
+    decode_digits(input, phases, reps)
+        .iter()
+        .fold(0, |offset, d| offset * 10 + d)
+}
+
+/// Same as `decode`, keeping any leading zeros of the message.
+pub fn decode_string(input: &[i32]) -> String {
+    decode_string_with(input, 100, 10_000)
+}
+
+/// Same as `decode_with`, keeping any leading zeros of the message.
+pub fn decode_string_with(input: &[i32], phases: usize, reps: usize) -> String {
+    decode_digits(input, phases, reps)
+        .iter()
+        .map(|d| std::char::from_digit(*d as u32, 10).unwrap())
+        .collect()
+}
+
+/// The eight digits of the message.
+/// Offsets in the first half of the signal fall back to the full `fft`.
+/// Panics if the message would run past the end of the signal.
+/// Algorithm from [u/paul2718](https://www.reddit.com/r/adventofcode/comments/ebf5cy/2019_day_16_part_2_understanding_how_to_come_up/fb4bvw4/).
+fn decode_digits(input: &[i32], phases: usize, reps: usize) -> Vec<i32> {
     let start = input[0..7].iter().fold(0, |offset, d| offset * 10 + d) as usize;
-    let end = input.len() * REPS;
-    assert!(start > end / 2);
-    assert!(start < end);
+    let end = input.len() * reps;
+    assert!(start + 8 <= end);
+    if start <= end / 2 {
+        // The shortcut below only holds in the second half of the signal.
+        let data: Vec<_> = input.iter().copied().cycle().take(end).collect();
+        return fft_phases(&data, &BASE_PATTERN, phases)[start..start + 8].to_vec();
+    }
     let mut data = Vec::with_capacity(end - start);
     for i in start..end {
         data.push(input[i % input.len()]);
     }
 
-    for _ in 0..PHASES {
+    for _ in 0..phases {
         for idx in (0..data.len() - 1).rev() {
             data[idx] = (data[idx] + data[idx + 1]) % 10;
         }
     }
 
-    data[0..8].iter().fold(0, |offset, d| offset * 10 + d)
+    data.truncate(8);
+    data
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const BASE_PATTERN: [i32; 4] = [0, 1, 0, -1];
-
     #[test]
     fn fft_pattern_first_digit() {
         assert_eq!(
@@ -96,6 +171,24 @@ mod tests {
         assert_eq!(phase_4, vec![0, 1, 0, 2, 9, 4, 9, 8]);
     }
 
+    #[test]
+    fn in_place() {
+        let mut buf = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let mut scratch = vec![];
+        fft_in_place(&mut buf, &BASE_PATTERN, &mut scratch);
+        assert_eq!(buf, vec![4, 8, 2, 2, 6, 1, 5, 8]);
+        fft_in_place(&mut buf, &BASE_PATTERN, &mut scratch);
+        assert_eq!(buf, vec![3, 4, 0, 4, 0, 4, 3, 8]);
+        let mut buf = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        fft_n(&mut buf, &BASE_PATTERN, 4);
+        let mut expected = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        for _ in 0..4 {
+            expected = fft(&expected, &BASE_PATTERN);
+        }
+        assert_eq!(buf, expected);
+        assert_eq!(buf, vec![0, 1, 0, 2, 9, 4, 9, 8]);
+    }
+
     fn parse_input(data: &str) -> Vec<i32> {
         data.chars()
             .map(|c| c.to_digit(10).unwrap() as i32)
@@ -105,41 +198,58 @@ mod tests {
     #[test]
     fn example_2() {
         let mut input = parse_input("80871224585914546619083218645595");
+        let output = fft_phases(&input, &BASE_PATTERN, 100);
         for _ in 0..100 {
             input = fft(&input, &BASE_PATTERN);
         }
+        assert_eq!(output, input);
         input.truncate(8);
         assert_eq!(input, parse_input("24176176"));
     }
 
+    #[test]
+    fn parallel() {
+        let input = parse_input("80871224585914546619083218645595");
+        assert_eq!(fft_par(&input, &BASE_PATTERN), fft(&input, &BASE_PATTERN));
+    }
+
+    #[test]
+    fn large_sums() {
+        // 3 * 400_000_001 * 2 doesn't fit in an i32.
+        let input = [3, 3];
+        let pattern = [400_000_001];
+        assert_eq!(fft(&input, &pattern), vec![6, 6]);
+        assert_eq!(fft64(&input, &pattern), fft(&input, &pattern));
+    }
+
     #[test]
     fn example_3() {
-        let mut input = parse_input("19617804207202209144916044189917");
-        for _ in 0..100 {
-            input = fft(&input, &BASE_PATTERN);
-        }
-        input.truncate(8);
-        assert_eq!(input, parse_input("73745418"));
+        let mut output = fft_phases(
+            &parse_input("19617804207202209144916044189917"),
+            &BASE_PATTERN,
+            100,
+        );
+        output.truncate(8);
+        assert_eq!(output, parse_input("73745418"));
     }
 
     #[test]
     fn example_4() {
-        let mut input = parse_input("69317163492948606335995924319873");
-        for _ in 0..100 {
-            input = fft(&input, &BASE_PATTERN);
-        }
-        input.truncate(8);
-        assert_eq!(input, parse_input("52432133"));
+        let mut output = fft_phases(
+            &parse_input("69317163492948606335995924319873"),
+            &BASE_PATTERN,
+            100,
+        );
+        output.truncate(8);
+        assert_eq!(output, parse_input("52432133"));
     }
 
     #[test]
     fn day_16_part_1() {
-        let mut input = parse_input(include_str!("input").lines().take(1).next().unwrap());
-        for _ in 0..100 {
-            input = fft(&input, &BASE_PATTERN);
-        }
-        input.truncate(8);
-        assert_eq!(input, parse_input("68317988"));
+        let input = parse_input(include_str!("input").lines().take(1).next().unwrap());
+        let mut output = fft_phases(&input, &BASE_PATTERN, 100);
+        output.truncate(8);
+        assert_eq!(output, parse_input("68317988"));
     }
 
     #[test]
@@ -163,6 +273,33 @@ mod tests {
         assert_eq!(output, 53_553_731);
     }
 
+    #[test]
+    fn decode_single_phase() {
+        let input = parse_input("00000209699940807407585447034323");
+        let phase_1 = fft(&input, &BASE_PATTERN);
+        let expected = phase_1[20..28].iter().fold(0, |offset, d| offset * 10 + d);
+        assert_eq!(decode_with(&input, 1, 1), expected);
+    }
+
+    #[test]
+    fn decode_first_half() {
+        let input = parse_input("00000109699940807407585447034323");
+        let data = fft_phases(
+            &[input.as_slice(), input.as_slice()].concat(),
+            &BASE_PATTERN,
+            4,
+        );
+        let expected = data[10..18].iter().fold(0, |offset, d| offset * 10 + d);
+        assert_eq!(decode_with(&input, 4, 2), expected);
+    }
+
+    #[test]
+    fn decode_leading_zero() {
+        let input = parse_input("00000120000011111113");
+        assert_eq!(decode_string_with(&input, 1, 1), "09876543");
+        assert_eq!(decode_with(&input, 1, 1), 9_876_543);
+    }
+
     #[test]
     fn day_16_part_2() {
         let input = parse_input(include_str!("input").lines().take(1).next().unwrap());
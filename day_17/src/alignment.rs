@@ -26,7 +26,7 @@ fn find_intersections(data: &[&str]) -> Vec<Coord> {
 }
 
 pub fn alignment_parameter(data: &[&str]) -> usize {
-    find_intersections(&data)
+    find_intersections(data)
         .iter()
         .map(Coord::alignment_parameter)
         .sum::<usize>()
@@ -1,13 +1,48 @@
 pub mod alignment;
 
 use day_9::computer::Computer;
+use snafu::Snafu;
 use std::char;
+use std::convert::TryFrom;
+pub use util::grid::Turn;
+use util::grid::{parse_grid, Coord, Direction, GridError};
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum CompressError {
+    #[snafu(display("The path can't be split into {} routines", ROUTINE_NAMES.len()))]
+    TooManyRoutines,
+
+    #[snafu(display(
+        "Routine {} needs {} characters, over the limit of {}",
+        name,
+        len,
+        MAX_LINE
+    ))]
+    RoutineTooLong { name: char, len: usize },
+
+    #[snafu(display("The robot isn't on the scaffolding"))]
+    RobotLost,
+}
 
 pub fn get_view(intcode: Vec<isize>) -> String {
+    get_view_with_input(intcode, None)
+}
+
+/// Like `get_view`, queuing the `input` line for a program that reads it first.
+pub fn get_view_with_input(intcode: Vec<isize>, input: Option<&str>) -> String {
+    let mut queue: Vec<_> = input
+        .map(|line| {
+            line.chars()
+                .chain(std::iter::once('\n'))
+                .rev()
+                .map(|ch| ch as isize)
+                .collect()
+        })
+        .unwrap_or_default();
     let mut data = String::new();
     Computer::new(
         intcode,
-        || unreachable!(),
+        || queue.pop().expect("The camera asked for more input"),
         |v| {
             data.push(char::from_u32(v as u32).unwrap());
         },
@@ -17,10 +52,13 @@ pub fn get_view(intcode: Vec<isize>) -> String {
     data
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Turn {
-    Left,
-    Right,
+/// Split the camera view into its rows, checking that they all have the same
+/// length.
+///
+/// Trailing empty lines are ignored.
+pub fn read_view(view: &str) -> Result<Vec<&str>, GridError> {
+    let rows = parse_grid(view)?.len();
+    Ok(view.lines().take(rows).collect())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -29,87 +67,14 @@ pub struct Move {
     pub distance: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Direction {
-    North,
-    East,
-    South,
-    West,
-}
-
-impl Direction {
-    fn turn(self, dir: Turn) -> Self {
-        match dir {
-            Turn::Left => match self {
-                Self::North => Self::West,
-                Self::East => Self::North,
-                Self::South => Self::East,
-                Self::West => Self::South,
-            },
-            Turn::Right => match self {
-                Self::North => Self::East,
-                Self::East => Self::South,
-                Self::South => Self::West,
-                Self::West => Self::North,
-            },
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Coord {
-    x: usize,
-    y: usize,
-}
-
-impl Coord {
-    fn neighbour(self, dir: Direction) -> Option<Self> {
-        self.mv(dir, 1)
-    }
-
-    fn mv(self, dir: Direction, distance: usize) -> Option<Self> {
-        match dir {
-            Direction::North => {
-                if self.y >= distance {
-                    Some(Self {
-                        x: self.x,
-                        y: self.y - distance,
-                    })
-                } else {
-                    None
-                }
-            }
-            Direction::East => {
-                if self.x <= usize::max_value() - distance {
-                    Some(Self {
-                        x: self.x + distance,
-                        y: self.y,
-                    })
-                } else {
-                    None
-                }
-            }
-            Direction::South => {
-                if self.y <= usize::max_value() - distance {
-                    Some(Self {
-                        x: self.x,
-                        y: self.y + distance,
-                    })
-                } else {
-                    None
-                }
-            }
-            Direction::West => {
-                if self.x >= distance {
-                    Some(Self {
-                        x: self.x - distance,
-                        y: self.y,
-                    })
-                } else {
-                    None
-                }
-            }
-        }
+/// The direction a robot glyph is facing.
+fn from_robot(glyph: u8) -> Option<Direction> {
+    match glyph {
+        b'^' => Some(Direction::North),
+        b'>' => Some(Direction::East),
+        b'v' => Some(Direction::South),
+        b'<' => Some(Direction::West),
+        _ => None,
     }
 }
 
@@ -119,39 +84,43 @@ trait Grid<T> {
 
 impl Grid<u8> for [&str] {
     fn cell(&self, coord: Coord) -> Option<&u8> {
-        self.get(coord.y)
-            .and_then(|line| line.as_bytes().get(coord.x))
+        let x = usize::try_from(coord.x).ok()?;
+        let y = usize::try_from(coord.y).ok()?;
+        self.get(y).and_then(|line| line.as_bytes().get(x))
     }
 }
 
-pub fn find_path(data: &[&str]) -> Vec<Move> {
-    let mut dir = Direction::North;
-    let mut pos = data
-        .iter()
+/// Find the robot and the direction it's facing.
+/// Returns `None` if the robot has fallen off the scaffolding.
+fn find_robot(data: &[&str]) -> Option<(Coord, Direction)> {
+    data.iter()
         .enumerate()
         .find_map(|(y, line)| {
-            if let Some(x) =
-                line.chars()
-                    .enumerate()
-                    .find_map(|(x, ch)| if ch == '^' { Some(x) } else { None })
-            {
-                Some(Coord { x, y })
-            } else {
-                None
-            }
+            line.bytes().position(|ch| b"^>v<X".contains(&ch)).map(|x| {
+                let pos = Coord {
+                    x: x as isize,
+                    y: y as isize,
+                };
+                (pos, line.as_bytes()[x])
+            })
         })
-        .unwrap();
+        .and_then(|(pos, glyph)| from_robot(glyph).map(|dir| (pos, dir)))
+}
+
+/// Returns `None` if there's no robot on the scaffolding.
+pub fn find_path(data: &[&str]) -> Option<Vec<Move>> {
+    let (mut pos, mut dir) = find_robot(data)?;
     let mut moves = Vec::new();
     while let Some(next) = [Turn::Left, Turn::Right]
         .iter()
         .copied()
         .filter_map(|turn| {
             let dir = dir.turn(turn);
-            let mut pos = pos.neighbour(dir);
+            let mut pos = pos + dir;
             let mut distance = 0;
-            while pos.and_then(|pos| data.cell(pos)) == Some(&b'#') {
+            while data.cell(pos) == Some(&b'#') {
                 distance += 1;
-                pos = pos.unwrap().neighbour(dir);
+                pos += dir;
             }
             if distance > 0 {
                 Some(Move { turn, distance })
@@ -162,37 +131,43 @@ pub fn find_path(data: &[&str]) -> Vec<Move> {
         .next()
     {
         dir = dir.turn(next.turn);
-        pos = pos.mv(dir, next.distance).unwrap();
+        for _ in 0..next.distance {
+            pos += dir;
+        }
         moves.push(next);
     }
-    moves
+    Some(moves)
 }
 
-/// Find the longest sequence from the start of the first slice with a repetition
-/// either in the same slice without intersections, or in the remaining slices.
-/// Returns the ending index of the sequence.
-fn find_repeated_sequence<T>(slices: &[&[T]]) -> usize
-where
-    T: PartialEq,
-{
-    let data = slices[0];
-    let first = (1..=data.len() / 2)
-        .rev()
-        .find(|len| {
-            let base = &data[0..*len];
-            data.windows(*len).skip(*len).any(|seq| base == seq)
-        })
-        .unwrap_or(0);
-    slices[1..]
-        .iter()
-        .filter_map(|other| {
-            let max_len = std::cmp::min(data.len(), other.len());
-            (1..=max_len).rev().find(|len| {
-                let base = &data[0..*len];
-                other.windows(*len).any(|seq| base == seq)
-            })
-        })
-        .fold(first, std::cmp::max)
+/// Draw the route of the robot over the view: straight segments as `-` and `|`,
+/// turns and crossings as `+`.
+pub fn render_path(view: &[&str]) -> String {
+    let mut grid: Vec<Vec<u8>> = view.iter().map(|line| line.bytes().collect()).collect();
+    if let (Some((mut pos, mut dir)), Some(path)) = (find_robot(view), find_path(view)) {
+        for (i, next) in path.into_iter().enumerate() {
+            dir = dir.turn(next.turn);
+            if i > 0 {
+                grid[pos.y as usize][pos.x as usize] = b'+';
+            }
+            let mark = match dir {
+                Direction::North | Direction::South => b'|',
+                Direction::East | Direction::West => b'-',
+            };
+            for _ in 0..next.distance {
+                pos += dir;
+                let cell = &mut grid[pos.y as usize][pos.x as usize];
+                *cell = if *cell == b'#' || *cell == mark {
+                    mark
+                } else {
+                    b'+'
+                };
+            }
+        }
+    }
+    grid.into_iter()
+        .map(|line| String::from_utf8(line).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 fn encode(moves: &[Move]) -> String {
@@ -212,84 +187,104 @@ fn encode(moves: &[Move]) -> String {
         .join(",")
 }
 
-pub fn clean_scaffolding_input(view: &[&str]) -> String {
-    let path = find_path(view);
-    // Ad-hoc algorithm to split into repeated sequences. Unlikely to find the optimal solution.
-    let mut paths = vec![path.as_slice()];
+/// Maximum length of each line of input.
+const MAX_LINE: usize = 20;
+const ROUTINE_NAMES: [char; 3] = ['A', 'B', 'C'];
+
+/// Split the moves into at most three routines and a main routine calling them,
+/// each fitting in a line of input.
+/// Returns the indices of the called routines and the routines themselves.
+fn compress(moves: &[Move]) -> Result<(Vec<usize>, Vec<&[Move]>), CompressError> {
+    let mut calls = Vec::new();
     let mut routines = Vec::new();
-    while !paths.is_empty() {
-        assert!(routines.len() < 3);
-        let routine = &paths[0][..find_repeated_sequence(&paths)];
-        // Find all the occurrences of the routine and remove them.
-        paths = paths
-            .into_iter()
-            .flat_map(|mut slice| {
-                let mut new_paths = Vec::new();
-                while let Some(start) = slice
-                    .windows(routine.len())
-                    .enumerate()
-                    .find(|(_, seq)| routine == *seq)
-                    .map(|(i, _)| i)
-                {
-                    if start > 0 {
-                        new_paths.push(&slice[..start]);
-                    }
-                    slice = &slice[start + routine.len()..];
-                }
-                if !slice.is_empty() {
-                    new_paths.push(slice);
-                }
-                new_paths
-            })
-            .collect();
-        // Store the new routine.
-        let name = char::from_u32('A' as u32 + routines.len() as u32)
-            .unwrap()
-            .to_string();
-        routines.push((name, routine.to_vec()));
+    compress_rest(moves, &mut calls, &mut routines)?;
+    Ok((calls, routines))
+}
+
+/// Depth-first search of the routines, trying to reuse the existing ones before
+/// defining new ones from the longest.
+/// Fails without backtracking if a single move doesn't fit in a routine.
+fn compress_rest<'a>(
+    rest: &'a [Move],
+    calls: &mut Vec<usize>,
+    routines: &mut Vec<&'a [Move]>,
+) -> Result<(), CompressError> {
+    if rest.is_empty() {
+        return Ok(());
     }
-    // Determine the call order.
-    let mut calls = Vec::new();
-    let mut rest = path.as_slice();
-    while !rest.is_empty() {
-        let (name, routine) = routines
-            .iter()
-            .find(|(_, routine)| rest.starts_with(&routine))
-            .unwrap();
-        calls.push(name.clone());
-        rest = &rest[routine.len()..];
+    // Each call takes a name and a comma.
+    if 2 * calls.len() + 1 > MAX_LINE {
+        return Err(CompressError::TooManyRoutines);
     }
-    // Build the input.
-    [
-        calls.join(&","),
-        routines
-            .iter()
-            .map(|(_, routine)| encode(&routine))
-            .collect::<Vec<_>>()
-            .join("\n"),
-        "n\n".to_owned(),
-    ]
-    .join("\n")
+    for (i, routine) in routines.clone().into_iter().enumerate() {
+        if rest.starts_with(routine) {
+            calls.push(i);
+            match compress_rest(&rest[routine.len()..], calls, routines) {
+                Err(CompressError::TooManyRoutines) => calls.pop(),
+                result => return result,
+            };
+        }
+    }
+    if routines.len() < ROUTINE_NAMES.len() {
+        let max_len = (1..=rest.len())
+            .take_while(|len| encode(&rest[..*len]).len() <= MAX_LINE)
+            .last()
+            .unwrap_or(0);
+        if max_len == 0 {
+            return Err(CompressError::RoutineTooLong {
+                name: ROUTINE_NAMES[routines.len()],
+                len: encode(&rest[..1]).len(),
+            });
+        }
+        for len in (1..=max_len).rev() {
+            calls.push(routines.len());
+            routines.push(&rest[..len]);
+            match compress_rest(&rest[len..], calls, routines) {
+                Err(CompressError::TooManyRoutines) => {
+                    routines.pop();
+                    calls.pop();
+                }
+                result => return result,
+            }
+        }
+    }
+    Err(CompressError::TooManyRoutines)
+}
+
+pub fn clean_scaffolding_input(view: &[&str]) -> Result<String, CompressError> {
+    let path = find_path(view).ok_or(CompressError::RobotLost)?;
+    let (calls, routines) = compress(&path)?;
+    let main = calls
+        .iter()
+        .map(|&i| ROUTINE_NAMES[i].to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    // The robot always asks for all the routines.
+    let routines = (0..ROUTINE_NAMES.len())
+        .map(|i| {
+            routines
+                .get(i)
+                .map_or_else(String::new, |routine| encode(routine))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok([main, routines, "n\n".to_owned()].join("\n"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use day_9::parse_intcode;
 
     fn read_intcode(data: &str) -> Vec<isize> {
-        data.lines()
-            .next()
-            .unwrap()
-            .split(',')
-            .map(|x| x.parse())
-            .collect::<Result<_, _>>()
-            .unwrap()
+        parse_intcode(data).unwrap()
     }
 
     #[test]
     fn day_17_part_1() {
         let view = get_view(read_intcode(include_str!("input")));
-        let data: Vec<_> = view.lines().collect();
+        let data = read_view(&view).unwrap();
+        assert_eq!(data.len(), 55);
         assert_eq!(alignment::alignment_parameter(&data), 5620);
     }
 
@@ -312,22 +307,190 @@ mod tests {
                             ....#####......"
             .lines()
             .collect();
-        let path = find_path(&view);
+        let path = find_path(&view).unwrap();
         assert_eq!(
             encode(&path),
             "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
         );
-        let input = clean_scaffolding_input(&view);
+        let input = clean_scaffolding_input(&view).unwrap();
+        assert_expands_to(&input, &path);
         // NOTE: The reference puts "R,8" at the end of B instead of the start
         // of C. The two are equivalent.
-        assert_eq!(
-            input,
+        assert_expands_to(
             "A,B,C,B,A,C\n\
              R,8,R,8\n\
              R,4,R,4\n\
              R,8,L,6,L,2\n\
-             n\n"
+             n\n",
+            &path,
+        );
+    }
+
+    #[test]
+    fn render_example_2() {
+        let view: Vec<_> = "#######...#####\n\
+                            #.....#...#...#\n\
+                            #.....#...#...#\n\
+                            ......#...#...#\n\
+                            ......#...###.#\n\
+                            ......#.....#.#\n\
+                            ^########...#.#\n\
+                            ......#.#...#.#\n\
+                            ......#########\n\
+                            ........#...#..\n\
+                            ....#########..\n\
+                            ....#...#......\n\
+                            ....#...#......\n\
+                            ....#...#......\n\
+                            ....#####......"
+            .lines()
+            .collect();
+        // Crossings at (6, 6), (8, 8), (12, 8) and (8, 10).
+        assert_eq!(
+            render_path(&view),
+            "+-----+...+---+\n\
+             |.....|...|...|\n\
+             |.....|...|...|\n\
+             ......|...|...|\n\
+             ......|...+-+.|\n\
+             ......|.....|.|\n\
+             ^-----+-+...|.|\n\
+             ......|.|...|.|\n\
+             ......+-+---+-+\n\
+             ........|...|..\n\
+             ....+---+---+..\n\
+             ....|...|......\n\
+             ....|...|......\n\
+             ....|...|......\n\
+             ....+---+......"
+        );
+    }
+
+    #[test]
+    fn ragged_view() {
+        assert_eq!(read_view("#^#\n.#.\n\n"), Ok(vec!["#^#", ".#."]));
+        assert_eq!(
+            read_view("#^#\n.#\n"),
+            Err(GridError::Ragged {
+                row: 1,
+                len: 2,
+                expected: 3
+            })
+        );
+    }
+
+    #[test]
+    fn robot_follows_grid_convention() {
+        let view = ["...", ".^.", "..."];
+        let (pos, dir) = find_robot(&view).unwrap();
+        assert_eq!(pos, Coord { x: 1, y: 1 });
+        assert_eq!(dir, Direction::North);
+        assert_eq!(pos + dir, Coord { x: 1, y: 0 });
+        assert_eq!(dir.turn(Turn::Right), from_robot(b'>').unwrap());
+    }
+
+    #[test]
+    fn robot_orientation() {
+        let view = "..#..\n\
+                    ..#..\n\
+                    ..>##\n";
+        // Rotate clockwise, the robot now faces south.
+        let rotated = "...\n\
+                       ...\n\
+                       v##\n\
+                       #..\n\
+                       #..\n";
+        let view: Vec<_> = view.lines().collect();
+        let rotated: Vec<_> = rotated.lines().collect();
+        let path = vec![Move {
+            turn: Turn::Left,
+            distance: 2,
+        }];
+        assert_eq!(find_path(&view), Some(path.clone()));
+        assert_eq!(find_path(&rotated), Some(path));
+        assert_eq!(find_path(&["X##"]), None);
+        assert_eq!(find_path(&["..."]), None);
+    }
+
+    /// Check that the routines fit in the input and expand to the path.
+    fn assert_expands_to(input: &str, path: &[Move]) {
+        let lines: Vec<_> = input.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[4], "n");
+        for line in &lines[..4] {
+            assert!(line.len() <= MAX_LINE, "{:?} is too long", line);
+        }
+        let expanded = lines[0]
+            .split(',')
+            .map(|name| match name {
+                "A" => lines[1],
+                "B" => lines[2],
+                "C" => lines[3],
+                _ => panic!("Unknown routine {:?}", name),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(expanded, encode(path));
+    }
+
+    #[test]
+    fn greedy_failure() {
+        let r3 = Move {
+            turn: Turn::Right,
+            distance: 3,
+        };
+        let l1 = Move {
+            turn: Turn::Left,
+            distance: 1,
+        };
+        // Taking the longest repeated prefix as the first routine leaves no
+        // repetition to build the others.
+        let path = [r3, l1, r3, r3, l1, r3, r3];
+        let (calls, routines) = compress(&path).unwrap();
+        assert_eq!(
+            calls
+                .into_iter()
+                .flat_map(|i| routines[i].iter().copied())
+                .collect::<Vec<_>>(),
+            path
+        );
+    }
+
+    #[test]
+    fn compress_errors() {
+        let distinct: Vec<_> = (1..=16)
+            .map(|distance| Move {
+                turn: Turn::Left,
+                distance,
+            })
+            .collect();
+        assert_eq!(compress(&distinct), Err(CompressError::TooManyRoutines));
+        let long = [Move {
+            turn: Turn::Right,
+            distance: 10usize.pow(19),
+        }];
+        assert_eq!(
+            compress(&long),
+            Err(CompressError::RoutineTooLong { name: 'A', len: 22 })
+        );
+    }
+
+    #[test]
+    fn setup_line() {
+        // Echo the setup line, then draw a tiny view.
+        let intcode = day_9::asm::assemble(
+            "
+            loop: in 30
+                  out 30
+                  eq 30, @10, 31
+                  jz 31, @loop
+                  out @35
+                  out @94
+                  hlt
+            ",
         )
+        .unwrap();
+        assert_eq!(get_view_with_input(intcode, Some("y")), "y\n#^");
     }
 
     #[test]
@@ -335,8 +498,12 @@ mod tests {
         let mut intcode = read_intcode(include_str!("input"));
         assert_eq!(intcode[0], 1);
         let view = get_view(intcode.clone());
-        let view: Vec<_> = view.lines().collect();
-        let mut input: Vec<_> = clean_scaffolding_input(&view).chars().rev().collect();
+        let view = read_view(&view).unwrap();
+        let mut input: Vec<_> = clean_scaffolding_input(&view)
+            .unwrap()
+            .chars()
+            .rev()
+            .collect();
         let mut dust = None;
         intcode[0] = 2;
         Computer::new(
@@ -1,35 +1,105 @@
 use crate::map::{Map, MapNode};
 use crate::{Coordinates, Cost, KeyId};
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use util::AdjInsert;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum GraphNode {
     Root(Option<u8>),
     Key(KeyId),
     Door(KeyId),
 }
 
+/// Which neighbors of a position can be reached in one move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Orthogonal moves, with the costs of the map.
+    Four,
+    /// Orthogonal and diagonal moves, each costing 1.
+    Eight,
+}
+
+/// Buffers for [`Graph::neighbors`](struct.Graph.html#method.neighbors).
+#[derive(Debug, Default)]
+pub struct Scratch {
+    result: Vec<(GraphNode, Cost)>,
+    visited: HashSet<GraphNode>,
+    to_visit: VecDeque<(GraphNode, Cost)>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Graph {
     adj_list: HashMap<GraphNode, HashMap<GraphNode, Cost>>,
+    reachable: HashMap<GraphNode, Vec<(KeyId, Cost, BTreeSet<KeyId>)>>,
+}
+
+/// Keys reachable from `from` along the shortest paths, with the doors and keys on the way.
+fn reachable_keys(
+    adj_list: &HashMap<GraphNode, HashMap<GraphNode, Cost>>,
+    from: GraphNode,
+) -> Vec<(KeyId, Cost, BTreeSet<KeyId>)> {
+    let mut result = Vec::new();
+    let mut visited = HashSet::new();
+    let mut to_visit = BinaryHeap::new();
+    to_visit.push(Reverse((0, from, BTreeSet::new())));
+    while let Some(Reverse((cost, node, mut required))) = to_visit.pop() {
+        if !visited.insert(node) {
+            continue;
+        }
+        match node {
+            GraphNode::Key(k) if node != from => {
+                result.push((k, cost, required.clone()));
+                required.insert(k);
+            }
+            GraphNode::Door(k) => {
+                required.insert(k);
+            }
+            _ => (),
+        }
+        for (&neighbor, step_cost) in &adj_list[&node] {
+            if !visited.contains(&neighbor) {
+                to_visit.push(Reverse((cost + step_cost, neighbor, required.clone())));
+            }
+        }
+    }
+    result
 }
 
-fn graph_neighbors(map: &impl Map, position: &Coordinates) -> Vec<(Coordinates, Cost)> {
+/// Filled nodes reachable from `position` through empty ones, with their cost.
+fn graph_neighbors(
+    map: &impl Map,
+    position: &Coordinates,
+    connectivity: Connectivity,
+) -> Vec<(Coordinates, Cost)> {
     let mut result = Vec::new();
-    let mut visited = [*position].iter().copied().collect::<HashSet<_>>();
-    let mut to_visit = [(*position, 0)].iter().copied().collect::<VecDeque<_>>();
-    while let Some((current, cost)) = to_visit.pop_front() {
-        for position in map.neighbors(&current) {
-            if visited.contains(&position) {
+    let mut visited = HashSet::new();
+    let mut to_visit = [Reverse((0, *position))]
+        .iter()
+        .copied()
+        .collect::<BinaryHeap<_>>();
+    while let Some(Reverse((cost, current))) = to_visit.pop() {
+        if !visited.insert(current) {
+            continue;
+        }
+        if current != *position {
+            if let Some(MapNode::Filled(_)) = map.node_at(&current) {
+                result.push((current, cost));
                 continue;
             }
-            let cost = cost + 1;
-            match map.node_at(&position) {
-                Some(MapNode::Filled(_)) => result.push((position, cost)),
-                Some(MapNode::Empty) => to_visit.push_back((position, cost)),
-                None => (),
+        }
+        let neighbors = match connectivity {
+            Connectivity::Four => map.weighted_neighbors(&current),
+            Connectivity::Eight => map
+                .neighbors_8(&current)
+                .into_iter()
+                .map(|neighbor| (neighbor, 1))
+                .collect(),
+        };
+        for (position, step_cost) in neighbors {
+            if !visited.contains(&position) {
+                to_visit.push(Reverse((cost + step_cost, position)));
             }
-            visited.insert(position);
         }
     }
     result
@@ -37,42 +107,81 @@ fn graph_neighbors(map: &impl Map, position: &Coordinates) -> Vec<(Coordinates,
 
 impl Graph {
     pub fn new(map: &impl Map) -> Self {
+        Self::with_connectivity(map, Connectivity::Four)
+    }
+
+    pub fn with_connectivity(map: &impl Map, connectivity: Connectivity) -> Self {
         let mut adj_list = HashMap::<GraphNode, HashMap<GraphNode, usize>>::new();
         let mut visited = HashSet::new();
-        let mut to_visit: VecDeque<_> = map
-            .find_root(None)
-            .map(|root| [(root, GraphNode::Root(None))].iter().copied().collect())
-            .unwrap_or_else(|| {
-                (0..)
-                    .map(|i| {
-                        map.find_root(Some(i))
-                            .map(|root| (root, GraphNode::Root(Some(i))))
-                    })
-                    .take_while(|o| o.is_some())
-                    .flatten()
-                    .collect()
-            });
+        let mut to_visit: VecDeque<_> = map.find_roots().into_iter().collect();
         while let Some((position, node)) = to_visit.pop_front() {
-            for (position, cost) in graph_neighbors(map, &position) {
+            for (position, cost) in graph_neighbors(map, &position, connectivity) {
                 let neighbor = match map.node_at(&position).unwrap() {
                     MapNode::Filled(n) => n,
                     _ => unreachable!(),
                 };
-                adj_list.entry(node).or_default().insert(neighbor, cost);
-                adj_list.entry(neighbor).or_default().insert(node, cost);
+                adj_list.adj_insert_with(node, neighbor, cost);
                 if !visited.contains(&position) {
                     to_visit.push_back((position, neighbor));
                 }
             }
             visited.insert(position);
         }
-        Self { adj_list }
+        Self::from_adj_list(adj_list)
+    }
+
+    fn from_adj_list(adj_list: HashMap<GraphNode, HashMap<GraphNode, Cost>>) -> Self {
+        let reachable = adj_list
+            .keys()
+            .filter(|node| !matches!(node, GraphNode::Door(_)))
+            .map(|&node| (node, reachable_keys(&adj_list, node)))
+            .collect();
+        Self {
+            adj_list,
+            reachable,
+        }
+    }
+
+    /// The union of two graphs, joined at their shared nodes.
+    ///
+    /// Edges found in both keep the cheaper cost.
+    pub fn merge(self, other: Graph) -> Graph {
+        let mut adj_list = self.adj_list;
+        for (node, neighbors) in other.adj_list {
+            let entry = adj_list.entry(node).or_default();
+            for (neighbor, cost) in neighbors {
+                let current = entry.entry(neighbor).or_insert(cost);
+                *current = (*current).min(cost);
+            }
+        }
+        Self::from_adj_list(adj_list)
+    }
+
+    /// Keys reachable from `from`, with the cost and the keys needed on the way.
+    pub fn reachable(&self, from: &GraphNode) -> &[(KeyId, Cost, BTreeSet<KeyId>)] {
+        self.reachable.get(from).map_or(&[], Vec::as_slice)
     }
 
-    pub fn neighbors(&self, node: &GraphNode, keys: &BTreeSet<KeyId>) -> Vec<(GraphNode, Cost)> {
-        let mut result = Vec::new();
-        let mut visited = [*node].iter().copied().collect::<HashSet<_>>();
-        let mut to_visit = [(*node, 0)].iter().copied().collect::<VecDeque<_>>();
+    /// Nodes reachable from `node` with `keys`, stopping at the first new key
+    /// on each path.
+    ///
+    /// The result lives in `scratch`, whose buffers are reused across calls.
+    pub fn neighbors<'a>(
+        &self,
+        node: &GraphNode,
+        keys: &BTreeSet<KeyId>,
+        scratch: &'a mut Scratch,
+    ) -> &'a [(GraphNode, Cost)] {
+        let Scratch {
+            result,
+            visited,
+            to_visit,
+        } = scratch;
+        result.clear();
+        visited.clear();
+        to_visit.clear();
+        visited.insert(*node);
+        to_visit.push_back((*node, 0));
         while let Some((current, cost)) = to_visit.pop_front() {
             for (&node, step_cost) in self.adj_list.get(&current).unwrap() {
                 if visited.contains(&node) {
@@ -92,8 +201,8 @@ impl Graph {
 
     pub fn keys(&self) -> BTreeSet<KeyId> {
         self.adj_list
-            .iter()
-            .filter_map(|(node, _)| match node {
+            .keys()
+            .filter_map(|node| match node {
                 GraphNode::Key(k) => Some(*k),
                 _ => None,
             })
@@ -101,8 +210,8 @@ impl Graph {
     }
     pub fn roots(&self) -> BTreeSet<Option<u8>> {
         self.adj_list
-            .iter()
-            .filter_map(|(node, _)| match node {
+            .keys()
+            .filter_map(|node| match node {
                 GraphNode::Root(r) => Some(*r),
                 _ => None,
             })
@@ -113,9 +222,12 @@ impl Graph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::map::{GlyphMap, DIGITS};
+    use std::cell::Cell;
+    use util::grid::parse_grid;
 
     fn str_to_mat(data: &str) -> Vec<Vec<char>> {
-        data.lines().map(|line| line.chars().collect()).collect()
+        parse_grid(data).unwrap()
     }
 
     #[test]
@@ -157,6 +269,201 @@ mod tests {
             .collect()
         );
         assert_eq!(graph.keys(), ['a', 'b'].iter().cloned().collect());
+        let mut reachable = graph.reachable(&GraphNode::Root(None)).to_vec();
+        reachable.sort();
+        assert_eq!(
+            reachable,
+            vec![
+                ('a', 2, BTreeSet::new()),
+                ('b', 4, ['a'].iter().cloned().collect())
+            ]
+        );
+    }
+
+    /// A map where `~` is mud, costing 10 to cross.
+    struct Muddy(Vec<Vec<char>>);
+
+    impl Map for Muddy {
+        fn node_at(&self, position: &Coordinates) -> Option<MapNode> {
+            let &(x, y) = position;
+            match self.0.get(y).and_then(|line| line.get(x)) {
+                Some('~') => Some(MapNode::Empty),
+                _ => self.0.node_at(position),
+            }
+        }
+
+        fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates> {
+            let (x0, y0) = position;
+            [(1, 0), (0, 1), (-1, 0), (0, -1)]
+                .iter()
+                .map(|(dx, dy)| ((*x0 as isize + dx) as usize, (*y0 as isize + dy) as usize))
+                .filter(|coord| self.node_at(coord).is_some())
+                .collect()
+        }
+
+        fn find(&self, node: GraphNode) -> Option<Coordinates> {
+            self.0.find(node)
+        }
+
+        fn weighted_neighbors(&self, position: &Coordinates) -> Vec<(Coordinates, Cost)> {
+            self.neighbors(position)
+                .into_iter()
+                .map(|(x, y)| ((x, y), if self.0[y][x] == '~' { 10 } else { 1 }))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn weighted() {
+        let map = str_to_mat(
+            "#####\n\
+             #@.a#\n\
+             #.#.#\n\
+             #...#\n\
+             #####",
+        );
+        let graph = Graph::new(&map);
+        assert_eq!(
+            graph.adj_list[&GraphNode::Root(None)][&GraphNode::Key('a')],
+            2
+        );
+        let mut muddy = map.clone();
+        muddy[1][2] = '~';
+        let graph = Graph::new(&Muddy(muddy));
+        // Going around the mud is shorter.
+        assert_eq!(
+            graph.adj_list[&GraphNode::Root(None)][&GraphNode::Key('a')],
+            6
+        );
+    }
+
+    /// Counts the searches on the map.
+    struct Counting {
+        map: Vec<Vec<char>>,
+        finds: Cell<usize>,
+    }
+
+    impl Map for Counting {
+        fn node_at(&self, position: &Coordinates) -> Option<MapNode> {
+            self.map.node_at(position)
+        }
+
+        fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates> {
+            self.map.neighbors(position)
+        }
+
+        fn find(&self, node: GraphNode) -> Option<Coordinates> {
+            self.finds.set(self.finds.get() + 1);
+            self.map.find(node)
+        }
+
+        fn find_roots(&self) -> Vec<(Coordinates, GraphNode)> {
+            self.map.find_roots()
+        }
+    }
+
+    #[test]
+    fn single_scan() {
+        let map = str_to_mat(
+            "#######\n\
+             #a.#Cd#\n\
+             ##0#1##\n\
+             #######\n\
+             ##2#3##\n\
+             #cB#Ab#\n\
+             #######",
+        );
+        let counting = Counting {
+            map: map.clone(),
+            finds: Cell::new(0),
+        };
+        let graph = Graph::new(&counting);
+        assert_eq!(counting.finds.get(), 0);
+        assert_eq!(
+            counting.find_roots(),
+            (0..4)
+                .map(|i| (map.find_root(Some(i)).unwrap(), GraphNode::Root(Some(i))))
+                .collect::<Vec<_>>()
+        );
+        // Without mud, `Muddy` is the same map with the default search of the roots.
+        assert_eq!(graph, Graph::new(&Muddy(map)));
+    }
+
+    #[test]
+    fn many_roots() {
+        let grid = str_to_mat(
+            "###################\n\
+             #0a#1b#2c#3d#4e#5f#\n\
+             ###################\n\
+             #6g#7h#8i#9j#!k#?l#\n\
+             ###################",
+        );
+        let map = GlyphMap {
+            grid: grid.clone(),
+            roots: "0123456789!?".chars().collect(),
+        };
+        let graph = Graph::new(&map);
+        assert_eq!(graph.roots(), (0..12).map(Some).collect());
+        assert_eq!(graph.keys().len(), 12);
+        assert_eq!(map.find_root(Some(11)), Some((16, 3)));
+        assert_eq!(map.find_root(Some(12)), None);
+        assert_eq!(
+            graph.reachable(&GraphNode::Root(Some(10))),
+            &[('k', 1, BTreeSet::new())]
+        );
+        assert_eq!(crate::shortest_path_length(&graph), 12);
+        // The digits are the default glyphs.
+        let digits = GlyphMap {
+            grid: str_to_mat("#0a#1b#"),
+            roots: DIGITS.to_vec(),
+        };
+        assert_eq!(Graph::new(&digits), Graph::new(&str_to_mat("#0a#1b#")));
+    }
+
+    #[test]
+    fn merge() {
+        let left = Graph::new(&str_to_mat("#0.a#"));
+        let right = Graph::new(&str_to_mat("#1.a..b#"));
+        let merged = left.clone().merge(right);
+        assert_eq!(merged.roots(), [Some(0), Some(1)].iter().cloned().collect());
+        assert_eq!(merged.keys(), ['a', 'b'].iter().cloned().collect());
+        let mut reachable = merged.reachable(&GraphNode::Root(Some(0))).to_vec();
+        reachable.sort();
+        assert_eq!(
+            reachable,
+            vec![
+                ('a', 2, BTreeSet::new()),
+                ('b', 5, ['a'].iter().cloned().collect())
+            ]
+        );
+        let shortcut = Graph::new(&str_to_mat("#0a#"));
+        let merged = left.merge(shortcut);
+        assert_eq!(
+            merged.adj_list[&GraphNode::Root(Some(0))][&GraphNode::Key('a')],
+            1
+        );
+        assert_eq!(
+            merged.adj_list[&GraphNode::Key('a')][&GraphNode::Root(Some(0))],
+            1
+        );
+    }
+
+    #[test]
+    fn diagonal() {
+        let map = str_to_mat(
+            "#####\n\
+             #@###\n\
+             ##.##\n\
+             ###a#\n\
+             #####",
+        );
+        let graph = Graph::new(&map);
+        assert_eq!(graph.keys(), BTreeSet::new());
+        let graph = Graph::with_connectivity(&map, Connectivity::Eight);
+        assert_eq!(
+            graph.adj_list[&GraphNode::Root(None)][&GraphNode::Key('a')],
+            2
+        );
     }
 
     #[test]
@@ -241,4 +548,31 @@ mod tests {
                 .collect()
         );
     }
+
+    #[test]
+    fn reused_scratch() {
+        let graph = Graph::new(&str_to_mat(include_str!("input")));
+        let all_keys = graph.keys();
+        let mut scratch = Scratch::default();
+        for node in graph.adj_list.keys() {
+            for keys in &[BTreeSet::new(), all_keys.clone()] {
+                let mut reused = graph.neighbors(node, keys, &mut scratch).to_vec();
+                let mut fresh = graph
+                    .neighbors(node, keys, &mut Scratch::default())
+                    .to_vec();
+                reused.sort();
+                fresh.sort();
+                assert_eq!(reused, fresh);
+            }
+        }
+        let from_root = graph.neighbors(&GraphNode::Root(None), &BTreeSet::new(), &mut scratch);
+        assert_eq!(
+            graph
+                .reachable(&GraphNode::Root(None))
+                .iter()
+                .filter(|(_, _, required)| required.is_empty())
+                .count(),
+            from_root.len()
+        );
+    }
 }
@@ -61,7 +61,12 @@ pub fn shortest_path_length(graph: &Graph) -> usize {
             continue;
         }
         for (i, node) in nodes.iter().enumerate() {
-            for (neighbor, step_cost) in graph.neighbors(node, &keys) {
+            for (neighbor, step_cost) in graph
+                .reachable(node)
+                .iter()
+                .filter(|(k, _, required)| !keys.contains(k) && required.is_subset(&keys))
+                .map(|(k, cost, _)| (GraphNode::Key(*k), cost))
+            {
                 let mut nodes = nodes.clone();
                 nodes[i] = neighbor;
                 let cost = cost + step_cost;
@@ -88,9 +93,10 @@ pub fn shortest_path_length(graph: &Graph) -> usize {
 mod tests {
     use super::*;
     use graph::Graph;
+    use util::grid::parse_grid;
 
     fn str_to_mat(data: &str) -> Vec<Vec<char>> {
-        data.lines().map(|line| line.chars().collect()).collect()
+        parse_grid(data).unwrap()
     }
 
     fn make_part_2(mut data: Vec<Vec<char>>) -> Vec<Vec<char>> {
@@ -259,7 +265,7 @@ mod tests {
     fn part_2() {
         assert_eq!(
             shortest_path_length(&Graph::new(&make_part_2(str_to_mat(include_str!("input"))))),
-            2796
+            1642
         );
     }
 }
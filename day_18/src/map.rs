@@ -1,5 +1,5 @@
 use crate::graph::GraphNode;
-use crate::Coordinates;
+use crate::{Coordinates, Cost};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MapNode {
@@ -12,47 +12,158 @@ pub trait Map {
     fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates>;
     fn find(&self, node: GraphNode) -> Option<Coordinates>;
 
+    /// Eight-connected neighbors, including the diagonals.
+    fn neighbors_8(&self, position: &Coordinates) -> Vec<Coordinates> {
+        let (x0, y0) = position;
+        [
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+        ]
+        .iter()
+        .map(|(dx, dy)| ((*x0 as isize + dx) as usize, (*y0 as isize + dy) as usize))
+        .filter(|coord| self.node_at(coord).is_some())
+        .collect()
+    }
+
+    /// Neighbors with the cost of moving to them.
+    fn weighted_neighbors(&self, position: &Coordinates) -> Vec<(Coordinates, Cost)> {
+        self.neighbors(position)
+            .into_iter()
+            .map(|neighbor| (neighbor, 1))
+            .collect()
+    }
+
     fn find_root(&self, index: Option<u8>) -> Option<Coordinates> {
         self.find(GraphNode::Root(index))
     }
+
+    /// Either the single root, or all the numbered ones.
+    fn find_roots(&self) -> Vec<(Coordinates, GraphNode)> {
+        self.find_root(None)
+            .map(|root| vec![(root, GraphNode::Root(None))])
+            .unwrap_or_else(|| {
+                (0..)
+                    .map(|i| {
+                        self.find_root(Some(i))
+                            .map(|root| (root, GraphNode::Root(Some(i))))
+                    })
+                    .take_while(|o| o.is_some())
+                    .flatten()
+                    .collect()
+            })
+    }
+}
+
+/// The labels of the numbered roots in a plain `Vec<Vec<char>>` map.
+pub const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
+/// A grid whose numbered roots are labeled by `roots`, the `i`-th glyph being
+/// root `i`, so that there can be more than ten of them.
+///
+/// The glyphs take precedence over keys and doors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlyphMap {
+    pub grid: Vec<Vec<char>>,
+    pub roots: Vec<char>,
+}
+
+fn grid_node_at(grid: &[Vec<char>], roots: &[char], position: &Coordinates) -> Option<MapNode> {
+    let &(x, y) = position;
+    grid.get(y)
+        .and_then(|line| line.get(x))
+        .and_then(|c| match c {
+            '@' => Some(MapNode::Filled(GraphNode::Root(None))),
+            '.' => Some(MapNode::Empty),
+            '#' => None,
+            c if roots.contains(c) => Some(MapNode::Filled(GraphNode::Root(Some(
+                roots.iter().position(|r| r == c).unwrap() as u8,
+            )))),
+            c @ 'a'..='z' => Some(MapNode::Filled(GraphNode::Key(*c))),
+            c @ 'A'..='Z' => Some(MapNode::Filled(GraphNode::Door(c.to_ascii_lowercase()))),
+            _ => unreachable!(),
+        })
+}
+
+fn grid_neighbors(map: &impl Map, position: &Coordinates) -> Vec<Coordinates> {
+    let (x0, y0) = position;
+    [(1, 0), (0, 1), (-1, 0), (0, -1)]
+        .iter()
+        .map(|(dx, dy)| ((*x0 as isize + dx) as usize, (*y0 as isize + dy) as usize))
+        .filter(|coord| map.node_at(coord).is_some())
+        .collect()
+}
+
+fn grid_find_roots(grid: &[Vec<char>], map: &impl Map) -> Vec<(Coordinates, GraphNode)> {
+    let mut roots: Vec<_> = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| (0..line.len()).map(move |x| (x, y)))
+        .filter_map(|position| match map.node_at(&position) {
+            Some(MapNode::Filled(root @ GraphNode::Root(_))) => Some((position, root)),
+            _ => None,
+        })
+        .collect();
+    roots.sort_by_key(|(_, root)| match root {
+        GraphNode::Root(index) => *index,
+        _ => unreachable!(),
+    });
+    roots
+}
+
+fn grid_find(grid: &[Vec<char>], roots: &[char], node: GraphNode) -> Option<Coordinates> {
+    let c = match node {
+        GraphNode::Root(c) => match c {
+            Some(i) => *roots.get(i as usize)?,
+            None => '@',
+        },
+        GraphNode::Key(c) => c,
+        GraphNode::Door(c) => c.to_ascii_uppercase(),
+    };
+    grid.iter().enumerate().find_map(|(y, line)| {
+        line.iter()
+            .enumerate()
+            .find_map(|(x, &c1)| if c1 == c { Some((x, y)) } else { None })
+    })
 }
 
 impl Map for Vec<Vec<char>> {
     fn node_at(&self, position: &Coordinates) -> Option<MapNode> {
-        let &(x, y) = position;
-        self.get(y)
-            .and_then(|line| line.get(x))
-            .and_then(|c| match c {
-                '@' => Some(MapNode::Filled(GraphNode::Root(None))),
-                '.' => Some(MapNode::Empty),
-                '#' => None,
-                c @ '0'..='9' => Some(MapNode::Filled(GraphNode::Root(Some(
-                    c.to_digit(10).unwrap() as u8,
-                )))),
-                c @ 'a'..='z' => Some(MapNode::Filled(GraphNode::Key(*c))),
-                c @ 'A'..='Z' => Some(MapNode::Filled(GraphNode::Door(c.to_ascii_lowercase()))),
-                _ => unreachable!(),
-            })
+        grid_node_at(self, &DIGITS, position)
     }
+
     fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates> {
-        let (x0, y0) = position;
-        [(1, 0), (0, 1), (-1, 0), (0, -1)]
-            .iter()
-            .map(|(dx, dy)| ((*x0 as isize + dx) as usize, (*y0 as isize + dy) as usize))
-            .filter(|coord| self.node_at(&coord).is_some())
-            .collect()
+        grid_neighbors(self, position)
+    }
+
+    fn find_roots(&self) -> Vec<(Coordinates, GraphNode)> {
+        grid_find_roots(self, self)
     }
 
     fn find(&self, node: GraphNode) -> Option<Coordinates> {
-        let c = match node {
-            GraphNode::Root(c) => c.map(|c| (c + '0' as u8) as char).unwrap_or('@'),
-            GraphNode::Key(c) => c,
-            GraphNode::Door(c) => c.to_ascii_uppercase(),
-        };
-        self.iter().enumerate().find_map(|(y, line)| {
-            line.iter()
-                .enumerate()
-                .find_map(|(x, &c1)| if c1 == c { Some((x, y)) } else { None })
-        })
+        grid_find(self, &DIGITS, node)
+    }
+}
+
+impl Map for GlyphMap {
+    fn node_at(&self, position: &Coordinates) -> Option<MapNode> {
+        grid_node_at(&self.grid, &self.roots, position)
+    }
+
+    fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates> {
+        grid_neighbors(self, position)
+    }
+
+    fn find_roots(&self) -> Vec<(Coordinates, GraphNode)> {
+        grid_find_roots(&self.grid, self)
+    }
+
+    fn find(&self, node: GraphNode) -> Option<Coordinates> {
+        grid_find(&self.grid, &self.roots, node)
     }
 }
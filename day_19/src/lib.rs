@@ -23,36 +23,77 @@ impl TractorBeam for Vec<isize> {
 }
 
 pub fn count_covered(beam: &impl TractorBeam, size: usize) -> usize {
-    (0..size)
-        .map(|y| (0..size).filter(|&x| beam.covers(x, y)).count())
+    count_covered_rect(beam, 0, 0, size, size)
+}
+
+/// Number of covered cells in the `w × h` rectangle with top left `(x0, y0)`.
+pub fn count_covered_rect(
+    beam: &impl TractorBeam,
+    x0: usize,
+    y0: usize,
+    w: usize,
+    h: usize,
+) -> usize {
+    (y0..y0 + h)
+        .map(|y| (x0..x0 + w).filter(|&x| beam.covers(x, y)).count())
         .sum()
 }
 
-fn fits(beam: &impl TractorBeam, coords: &(usize, usize), size: usize) -> bool {
+/// Draw the `w × h` window at the origin, with `#` for covered cells.
+pub fn render(beam: &impl TractorBeam, w: usize, h: usize) -> String {
+    (0..h)
+        .map(|y| {
+            (0..w)
+                .map(|x| if beam.covers(x, y) { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A corner of a box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+}
+
+/// Whether a box fits in the beam, or the first corner that falls outside.
+pub type FitResult = Result<(), Corner>;
+
+/// Check whether the `size × size` box with top left `coords` fits in the beam.
+pub fn fit_report(beam: &impl TractorBeam, coords: &(usize, usize), size: usize) -> FitResult {
     let &(x, y) = coords;
     // No need to check bottom right per definition of the beam.
-    beam.covers(x, y) && beam.covers(x + size - 1, y) && beam.covers(x, y + size - 1)
+    let corners = [
+        (Corner::TopLeft, (x, y)),
+        (Corner::TopRight, (x + size - 1, y)),
+        (Corner::BottomLeft, (x, y + size - 1)),
+    ];
+    match corners.iter().find(|(_, (x, y))| !beam.covers(*x, *y)) {
+        Some((corner, _)) => Err(*corner),
+        None => Ok(()),
+    }
+}
+
+fn fits(beam: &impl TractorBeam, coords: &(usize, usize), size: usize) -> bool {
+    fit_report(beam, coords, size).is_ok()
 }
 
 pub fn find_box(beam: &impl TractorBeam, size: usize, max: usize) -> Option<(usize, usize)> {
     (0..max)
         .flat_map(|y| (0..=y).map(move |x| (x, y)))
-        .filter(|coords| fits(beam, coords, size))
-        .next()
+        .find(|coords| fits(beam, coords, size))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use day_9::parse_intcode;
 
     fn str_to_intcode(data: &str) -> Vec<isize> {
-        data.lines()
-            .next()
-            .unwrap()
-            .split(",")
-            .map(|x| x.parse())
-            .collect::<Result<_, _>>()
-            .unwrap()
+        parse_intcode(data).unwrap()
     }
 
     #[test]
@@ -65,36 +106,55 @@ mod tests {
 
     impl TractorBeam for &str {
         fn covers(&self, x: usize, y: usize) -> bool {
-            match self
-                .lines()
-                .skip(y)
-                .next()
-                .and_then(|row| row.chars().skip(x).next())
-            {
-                Some('#') => true,
-                _ => false,
-            }
+            matches!(
+                self.lines().nth(y).and_then(|row| row.chars().nth(x)),
+                Some('#')
+            )
         }
     }
 
+    const SMALL: &str = "#..............\n\
+                        ...............\n\
+                        ...............\n\
+                        ..#............\n\
+                        ...#...........\n\
+                        ....#..........\n\
+                        ....##.........\n\
+                        .....#.........\n\
+                        ......#........\n\
+                        ......##.......\n\
+                        .......##......\n\
+                        ........##.....\n\
+                        ........###....\n\
+                        .........##....\n\
+                        ..........##...";
+
     #[test]
     fn small_find() {
-        let data = "#..............\n\
-                    ...............\n\
-                    ...............\n\
-                    ..#............\n\
-                    ...#...........\n\
-                    ....#..........\n\
-                    ....##.........\n\
-                    .....#.........\n\
-                    ......#........\n\
-                    ......##.......\n\
-                    .......##......\n\
-                    ........##.....\n\
-                    ........###....\n\
-                    .........##....\n\
-                    ..........##...";
-        assert_eq!(find_box(&data, 2, 13).unwrap(), (8, 11));
+        assert_eq!(find_box(&SMALL, 2, 13).unwrap(), (8, 11));
+    }
+
+    #[test]
+    fn small_fit_report() {
+        assert_eq!(fit_report(&SMALL, &(8, 11), 2), Ok(()));
+        assert_eq!(fit_report(&SMALL, &(9, 11), 2), Err(Corner::TopRight));
+        assert_eq!(fit_report(&SMALL, &(7, 10), 2), Err(Corner::BottomLeft));
+        assert_eq!(fit_report(&SMALL, &(0, 1), 2), Err(Corner::TopLeft));
+    }
+
+    #[test]
+    fn small_rect() {
+        assert_eq!(count_covered_rect(&SMALL, 4, 4, 4, 4), 4);
+        assert_eq!(count_covered_rect(&SMALL, 0, 0, 15, 15), 21);
+        assert_eq!(
+            count_covered_rect(&SMALL, 0, 0, 3, 3),
+            count_covered(&SMALL, 3)
+        );
+    }
+
+    #[test]
+    fn small_render() {
+        assert_eq!(render(&SMALL, 15, 15), SMALL);
     }
 
     #[test]
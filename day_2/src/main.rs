@@ -2,17 +2,28 @@
 extern crate clap;
 mod computer;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg};
+use day_9::parse_intcode;
+use std::fs;
 
 use computer::find_noun_verb;
 
 fn main() {
     let matches = App::new("day_2")
-        .version(&crate_version!()[..])
+        .version(crate_version!())
+        // The result is the only positional argument when using --file.
+        .setting(AppSettings::AllowMissingPositional)
         .arg(
             Arg::with_name("intcode")
                 .help("the Intcode to run")
-                .required(true),
+                .required_unless("file"),
+        )
+        .arg(
+            Arg::with_name("file")
+                .long("file")
+                .takes_value(true)
+                .conflicts_with("intcode")
+                .help("a file with the Intcode on its first line"),
         )
         .arg(
             Arg::with_name("result")
@@ -21,14 +32,12 @@ fn main() {
         )
         .get_matches();
 
-    let intcode: Vec<usize> = matches
-        .value_of("intcode")
-        .unwrap()
-        .split(',')
-        .map(|x| x.parse())
-        .collect::<Result<_, _>>()
-        .unwrap();
-    let result = value_t!(matches, "result", usize).unwrap();
+    let data = match matches.value_of("file") {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => matches.value_of("intcode").unwrap().to_owned(),
+    };
+    let intcode: Vec<isize> = parse_intcode(data.lines().next().unwrap_or("")).unwrap();
+    let result = value_t!(matches, "result", isize).unwrap();
     if let Some((noun, verb)) = find_noun_verb(intcode, result) {
         println!("{}", 100 * noun + verb);
     } else {
@@ -0,0 +1,142 @@
+use crate::map::{Map, MapNode};
+use crate::{Coordinates, Cost, Label};
+use std::collections::{HashMap, HashSet, VecDeque};
+use util::AdjInsert;
+
+/// A side of a portal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GraphNode {
+    Outer(Label),
+    Inner(Label),
+}
+
+impl GraphNode {
+    /// The other side of the portal.
+    pub fn warp(self) -> Self {
+        match self {
+            GraphNode::Outer(label) => GraphNode::Inner(label),
+            GraphNode::Inner(label) => GraphNode::Outer(label),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Graph {
+    adj_list: HashMap<GraphNode, HashMap<GraphNode, Cost>>,
+}
+
+/// Portals reachable by walking, passing by other portals.
+fn graph_neighbors(map: &impl Map, position: &Coordinates) -> Vec<(Coordinates, Cost)> {
+    let mut result = Vec::new();
+    let mut visited = [*position].iter().copied().collect::<HashSet<_>>();
+    let mut to_visit = [(*position, 0)].iter().copied().collect::<VecDeque<_>>();
+    while let Some((current, cost)) = to_visit.pop_front() {
+        for position in map.neighbors(&current) {
+            if visited.contains(&position) {
+                continue;
+            }
+            let cost = cost + 1;
+            if let Some(MapNode::Filled(_)) = map.node_at(&position) {
+                result.push((position, cost));
+            }
+            to_visit.push_back((position, cost));
+            visited.insert(position);
+        }
+    }
+    result
+}
+
+impl Graph {
+    pub fn new(map: &impl Map) -> Self {
+        let mut adj_list = HashMap::<GraphNode, HashMap<GraphNode, Cost>>::new();
+        let mut visited = HashSet::new();
+        let mut to_visit: VecDeque<_> = map
+            .find_entrance()
+            .map(|position| (position, GraphNode::Outer(['A', 'A'])))
+            .into_iter()
+            .collect();
+        while let Some((position, node)) = to_visit.pop_front() {
+            if !visited.insert(position) {
+                continue;
+            }
+            adj_list.entry(node).or_default();
+            for (position, cost) in graph_neighbors(map, &position) {
+                let neighbor = match map.node_at(&position).unwrap() {
+                    MapNode::Filled(n) => n,
+                    _ => unreachable!(),
+                };
+                adj_list.adj_insert_with(node, neighbor, cost);
+                to_visit.push_back((position, neighbor));
+            }
+            if let Some(position) = map.find(node.warp()) {
+                to_visit.push_back((position, node.warp()));
+            }
+        }
+        Self { adj_list }
+    }
+
+    /// Number of portal sides.
+    pub fn len(&self) -> usize {
+        self.adj_list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adj_list.is_empty()
+    }
+
+    pub fn contains(&self, node: &GraphNode) -> bool {
+        self.adj_list.contains_key(node)
+    }
+
+    /// Portals reachable by walking from `node`.
+    pub fn neighbors(&self, node: &GraphNode) -> Vec<(GraphNode, Cost)> {
+        self.adj_list
+            .get(node)
+            .into_iter()
+            .flatten()
+            .map(|(&node, &cost)| (node, cost))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Maze;
+
+    #[test]
+    fn small() {
+        let maze = Maze::new(
+            r"   A
+   A
+###.###
+#.....#
+###.###
+   Z
+   Z",
+        );
+        let graph = Graph::new(&maze);
+        assert_eq!(
+            graph.adj_list,
+            [
+                (
+                    GraphNode::Outer(['A', 'A']),
+                    [(GraphNode::Outer(['Z', 'Z']), 2)]
+                        .iter()
+                        .copied()
+                        .collect()
+                ),
+                (
+                    GraphNode::Outer(['Z', 'Z']),
+                    [(GraphNode::Outer(['A', 'A']), 2)]
+                        .iter()
+                        .copied()
+                        .collect()
+                ),
+            ]
+            .iter()
+            .cloned()
+            .collect()
+        );
+    }
+}
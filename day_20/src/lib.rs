@@ -0,0 +1,209 @@
+pub mod graph;
+pub mod map;
+
+use graph::{Graph, GraphNode};
+use map::Maze;
+use std::collections::{BinaryHeap, HashSet};
+
+pub type Coordinates = (usize, usize);
+pub type Cost = usize;
+pub type Label = [char; 2];
+
+const ENTRANCE: GraphNode = GraphNode::Outer(['A', 'A']);
+const EXIT: GraphNode = GraphNode::Outer(['Z', 'Z']);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    node: GraphNode,
+    depth: usize,
+    cost: Cost,
+}
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .cost
+            .cmp(&self.cost)
+            .then_with(|| self.depth.cmp(&other.depth))
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Number of steps from `AA` to `ZZ`.
+pub fn shortest_path(maze: &Maze) -> Option<Cost> {
+    search(&Graph::new(maze), None)
+}
+
+/// Number of steps from `AA` to `ZZ` when inner portals lead one level deeper
+/// and outer ones one level up.
+pub fn shortest_recursive(maze: &Maze) -> Option<Cost> {
+    let graph = Graph::new(maze);
+    // Going deeper than the number of portals only repeats the same paths.
+    let max_depth = graph.len();
+    search(&graph, Some(max_depth))
+}
+
+/// Dijkstra over the portals, with levels up to `max_depth` if recursive.
+fn search(graph: &Graph, max_depth: Option<usize>) -> Option<Cost> {
+    let mut visited = HashSet::new();
+    let mut to_visit: BinaryHeap<_> = [State {
+        node: ENTRANCE,
+        depth: 0,
+        cost: 0,
+    }]
+    .iter()
+    .cloned()
+    .collect();
+    while let Some(State { node, depth, cost }) = to_visit.pop() {
+        if node == EXIT && depth == 0 {
+            return Some(cost);
+        }
+        if !visited.insert((node, depth)) {
+            continue;
+        }
+        for (neighbor, step_cost) in graph.neighbors(&node) {
+            to_visit.push(State {
+                node: neighbor,
+                depth,
+                cost: cost + step_cost,
+            });
+        }
+        let depth = match (max_depth, node) {
+            (None, _) => Some(depth),
+            (Some(max_depth), GraphNode::Inner(_)) if depth < max_depth => Some(depth + 1),
+            (Some(_), GraphNode::Inner(_)) => None,
+            (Some(_), GraphNode::Outer(_)) => depth.checked_sub(1),
+        };
+        // Going through a portal takes one step.
+        if let (Some(depth), true) = (depth, graph.contains(&node.warp())) {
+            to_visit.push(State {
+                node: node.warp(),
+                depth,
+                cost: cost + 1,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_1: &str = r"         A
+         A
+  #######.#########
+  #######.........#
+  #######.#######.#
+  #######.#######.#
+  #######.#######.#
+  #####  B    ###.#
+BC...##  C    ###.#
+  ##.##       ###.#
+  ##...DE  F  ###.#
+  #####    G  ###.#
+  #########.#####.#
+DE..#######...###.#
+  #.#########.###.#
+FG..#########.....#
+  ###########.#####
+             Z
+             Z";
+
+    const EXAMPLE_2: &str = r"                   A
+                   A
+  #################.#############
+  #.#...#...................#.#.#
+  #.#.#.###.###.###.#########.#.#
+  #.#.#.......#...#.....#.#.#...#
+  #.#########.###.#####.#.#.###.#
+  #.............#.#.....#.......#
+  ###.###########.###.#.#.#.#####
+  #.....#        A   C    #.#.#.#
+  #######        S   P    #####.#
+  #.#...#                 #......VT
+  #.#.#.#                 #.#####
+  #...#.#               YN....#.#
+  #.###.#                 #####.#
+DI....#.#                 #.....#
+  #####.#                 #.###.#
+ZZ......#               QG....#..AS
+  ###.###                 #######
+JO..#.#.#                 #.....#
+  #.#.#.#                 ###.#.#
+  #...#..DI             BU....#..LF
+  #####.#                 #.#####
+YN......#               VT..#....QG
+  #.###.#                 #.###.#
+  #.#...#                 #.....#
+  ###.###    J L     J    #.#.###
+  #.....#    O F     P    #.#...#
+  #.###.#####.#.#####.#####.###.#
+  #...#.#.#...#.....#.....#.#...#
+  #.#####.###.###.#.#.#########.#
+  #...#.#.....#...#.#.#.#.....#.#
+  #.###.#####.###.###.#.#.#######
+  #.#.........#...#.............#
+  #########.###.###.#############
+           B   J   C
+           U   P   P";
+
+    const EXAMPLE_3: &str = r"             Z L X W       C
+             Z P Q B       K
+  ###########.#.#.#.#######.###############
+  #...#.......#.#.......#.#.......#.#.#...#
+  ###.#.#.#.#.#.#.#.###.#.#.#######.#.#.###
+  #.#...#.#.#...#.#.#...#...#...#.#.......#
+  #.###.#######.###.###.#.###.###.#.#######
+  #...#.......#.#...#...#.............#...#
+  #.#########.#######.#.#######.#######.###
+  #...#.#    F       R I       Z    #.#.#.#
+  #.###.#    D       E C       H    #.#.#.#
+  #.#...#                           #...#.#
+  #.###.#                           #.###.#
+  #.#....OA                       WB..#.#..ZH
+  #.###.#                           #.#.#.#
+CJ......#                           #.....#
+  #######                           #######
+  #.#....CK                         #......IC
+  #.###.#                           #.###.#
+  #.....#                           #...#.#
+  ###.###                           #.#.#.#
+XF....#.#                         RF..#.#.#
+  #####.#                           #######
+  #......CJ                       NM..#...#
+  ###.#.#                           #.###.#
+RE....#.#                           #......RF
+  ###.###        X   X       L      #.#.#.#
+  #.....#        F   Q       P      #.#.#.#
+  ###.###########.###.#######.#########.###
+  #.....#...#.....#.......#...#.....#.#...#
+  #####.#.###.#######.#######.###.###.#.#.#
+  #.......#.......#.#.#.#.#...#...#...#.#.#
+  #####.###.#####.#.#.#.#.###.###.#.###.###
+  #.......#.....#.#...#...............#...#
+  #############.#.#.###.###################
+               A O F   N
+               A A D   M";
+
+    #[test]
+    fn example_1() {
+        assert_eq!(shortest_path(&Maze::new(EXAMPLE_1)), Some(23));
+        assert_eq!(shortest_recursive(&Maze::new(EXAMPLE_1)), Some(26));
+    }
+
+    #[test]
+    fn example_2() {
+        assert_eq!(shortest_path(&Maze::new(EXAMPLE_2)), Some(58));
+        assert_eq!(shortest_recursive(&Maze::new(EXAMPLE_2)), None);
+    }
+
+    #[test]
+    fn example_3() {
+        assert_eq!(shortest_recursive(&Maze::new(EXAMPLE_3)), Some(396));
+    }
+}
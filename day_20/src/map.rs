@@ -0,0 +1,104 @@
+use crate::graph::GraphNode;
+use crate::Coordinates;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapNode {
+    Filled(GraphNode),
+    Empty,
+}
+
+pub trait Map {
+    fn node_at(&self, position: &Coordinates) -> Option<MapNode>;
+    fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates>;
+    fn find(&self, node: GraphNode) -> Option<Coordinates>;
+
+    fn find_entrance(&self) -> Option<Coordinates> {
+        self.find(GraphNode::Outer(['A', 'A']))
+    }
+}
+
+/// A donut maze, with the portals next to their open cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Maze {
+    cells: Vec<Vec<char>>,
+    portals: HashMap<Coordinates, GraphNode>,
+}
+
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+fn offset(position: &Coordinates, (dx, dy): (isize, isize)) -> Option<Coordinates> {
+    let (x, y) = position;
+    let x = *x as isize + dx;
+    let y = *y as isize + dy;
+    if x >= 0 && y >= 0 {
+        Some((x as usize, y as usize))
+    } else {
+        None
+    }
+}
+
+impl Maze {
+    pub fn new(data: &str) -> Self {
+        let cells: Vec<Vec<char>> = data.lines().map(|line| line.chars().collect()).collect();
+        let cell = |position: Option<Coordinates>| {
+            position.and_then(|(x, y)| cells.get(y).and_then(|line| line.get(x)).copied())
+        };
+        let mut portals = HashMap::new();
+        for (y, line) in cells.iter().enumerate() {
+            for (x, _) in line.iter().enumerate().filter(|(_, &c)| c == '.') {
+                for &direction in &DIRECTIONS {
+                    let near = offset(&(x, y), direction);
+                    let far = near.and_then(|near| offset(&near, direction));
+                    if let (Some(a), Some(b)) = (cell(near), cell(far)) {
+                        if a.is_ascii_uppercase() && b.is_ascii_uppercase() {
+                            // Labels read left to right or top to bottom.
+                            let label = if direction.0 + direction.1 > 0 {
+                                [a, b]
+                            } else {
+                                [b, a]
+                            };
+                            // Outer labels are on the edge of the map.
+                            let beyond = far.and_then(|far| offset(&far, direction));
+                            let node = if cell(beyond).is_some() {
+                                GraphNode::Inner(label)
+                            } else {
+                                GraphNode::Outer(label)
+                            };
+                            portals.insert((x, y), node);
+                        }
+                    }
+                }
+            }
+        }
+        Self { cells, portals }
+    }
+}
+
+impl Map for Maze {
+    fn node_at(&self, position: &Coordinates) -> Option<MapNode> {
+        let &(x, y) = position;
+        match self.cells.get(y).and_then(|line| line.get(x)) {
+            Some('.') => Some(
+                self.portals
+                    .get(position)
+                    .map_or(MapNode::Empty, |&node| MapNode::Filled(node)),
+            ),
+            _ => None,
+        }
+    }
+
+    fn neighbors(&self, position: &Coordinates) -> Vec<Coordinates> {
+        DIRECTIONS
+            .iter()
+            .filter_map(|&direction| offset(position, direction))
+            .filter(|coord| self.node_at(coord).is_some())
+            .collect()
+    }
+
+    fn find(&self, node: GraphNode) -> Option<Coordinates> {
+        self.portals
+            .iter()
+            .find_map(|(&position, &other)| if other == node { Some(position) } else { None })
+    }
+}
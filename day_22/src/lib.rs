@@ -0,0 +1,235 @@
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum ParseError {
+    #[snafu(display("Line {} is not a technique: {:?}", line, text))]
+    TechniqueInvalid { line: usize, text: String },
+
+    #[snafu(display("Invalid amount on line {}: {}", line, source))]
+    AmountInvalid {
+        line: usize,
+        source: std::num::ParseIntError,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Technique {
+    NewStack,
+    Cut(i64),
+    Increment(i64),
+}
+
+pub fn parse_techniques(data: &str) -> Result<Vec<Technique>, ParseError> {
+    data.lines()
+        .enumerate()
+        .map(|(line, text)| {
+            let text = text.trim();
+            let amount =
+                |prefix: &str| text[prefix.len()..].parse().context(AmountInvalid { line });
+            if text == "deal into new stack" {
+                Ok(Technique::NewStack)
+            } else if text.starts_with("cut ") {
+                Ok(Technique::Cut(amount("cut ")?))
+            } else if text.starts_with("deal with increment ") {
+                Ok(Technique::Increment(amount("deal with increment ")?))
+            } else {
+                Err(ParseError::TechniqueInvalid {
+                    line,
+                    text: text.to_owned(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Shuffle moving the card in position `x` to `a * x + b` in a deck of `m` cards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shuffle {
+    a: i128,
+    b: i128,
+    m: i128,
+}
+
+impl Shuffle {
+    pub fn identity(deck_size: u64) -> Self {
+        Self {
+            a: 1,
+            b: 0,
+            m: i128::from(deck_size),
+        }
+    }
+
+    pub fn from_technique(technique: Technique, deck_size: u64) -> Self {
+        let m = i128::from(deck_size);
+        let (a, b) = match technique {
+            Technique::NewStack => (-1, -1),
+            Technique::Cut(n) => (1, -i128::from(n)),
+            Technique::Increment(n) => (i128::from(n), 0),
+        };
+        Self {
+            a: a.rem_euclid(m),
+            b: b.rem_euclid(m),
+            m,
+        }
+    }
+
+    /// Apply `self`, then `other`.
+    pub fn then(self, other: Self) -> Self {
+        assert_eq!(self.m, other.m);
+        Self {
+            a: (other.a * self.a).rem_euclid(self.m),
+            b: (other.a * self.b + other.b).rem_euclid(self.m),
+            m: self.m,
+        }
+    }
+
+    /// Repeat the shuffle `times` times.
+    pub fn repeat(self, mut times: u64) -> Self {
+        let mut result = Self::identity(self.m as u64);
+        let mut base = self;
+        while times > 0 {
+            if times % 2 == 1 {
+                result = result.then(base);
+            }
+            base = base.then(base);
+            times /= 2;
+        }
+        result
+    }
+
+    /// The shuffle undoing `self`. Requires a prime number of cards.
+    pub fn inverse(self) -> Self {
+        // Fermat's little theorem.
+        let inv_a = mod_pow(self.a, self.m - 2, self.m);
+        Self {
+            a: inv_a,
+            b: (-inv_a * self.b).rem_euclid(self.m),
+            m: self.m,
+        }
+    }
+
+    /// The position of the card at `position` after the shuffle.
+    pub fn apply(&self, position: u64) -> u64 {
+        (self.a * i128::from(position) + self.b).rem_euclid(self.m) as u64
+    }
+}
+
+fn mod_pow(mut base: i128, mut exp: i128, m: i128) -> i128 {
+    let mut result = 1;
+    base = base.rem_euclid(m);
+    while exp > 0 {
+        if exp % 2 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp /= 2;
+    }
+    result
+}
+
+/// Fold the techniques into a single shuffle.
+pub fn compose(techniques: &[Technique], deck_size: u64) -> Shuffle {
+    techniques
+        .iter()
+        .fold(Shuffle::identity(deck_size), |shuffle, &technique| {
+            shuffle.then(Shuffle::from_technique(technique, deck_size))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deck_after(data: &str, deck_size: u64) -> Vec<u64> {
+        let shuffle = compose(&parse_techniques(data).unwrap(), deck_size);
+        let mut deck = vec![0; deck_size as usize];
+        for card in 0..deck_size {
+            deck[shuffle.apply(card) as usize] = card;
+        }
+        deck
+    }
+
+    #[test]
+    fn example_1() {
+        assert_eq!(
+            deck_after(
+                "deal with increment 7\n\
+                 deal into new stack\n\
+                 deal into new stack",
+                10
+            ),
+            vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]
+        );
+    }
+
+    #[test]
+    fn example_2() {
+        assert_eq!(
+            deck_after(
+                "cut 6\n\
+                 deal with increment 7\n\
+                 deal into new stack",
+                10
+            ),
+            vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]
+        );
+    }
+
+    #[test]
+    fn example_3() {
+        assert_eq!(
+            deck_after(
+                "deal with increment 7\n\
+                 deal with increment 9\n\
+                 cut -2",
+                10
+            ),
+            vec![6, 3, 0, 7, 4, 1, 8, 5, 2, 9]
+        );
+    }
+
+    #[test]
+    fn example_4() {
+        assert_eq!(
+            deck_after(
+                "deal into new stack\n\
+                 cut -2\n\
+                 deal with increment 7\n\
+                 cut 8\n\
+                 cut -4\n\
+                 deal with increment 7\n\
+                 cut 3\n\
+                 deal with increment 9\n\
+                 deal with increment 3\n\
+                 cut -1",
+                10
+            ),
+            vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]
+        );
+    }
+
+    #[test]
+    fn repeat_and_inverse() {
+        let techniques = parse_techniques(
+            "deal into new stack\n\
+             cut -2\n\
+             deal with increment 7",
+        )
+        .unwrap();
+        let shuffle = compose(&techniques, 10007);
+        let mut position = 2019;
+        for _ in 0..1000 {
+            position = shuffle.apply(position);
+        }
+        let repeated = shuffle.repeat(1000);
+        assert_eq!(repeated.apply(2019), position);
+        assert_eq!(repeated.inverse().apply(position), 2019);
+        assert_eq!(shuffle.then(shuffle.inverse()), Shuffle::identity(10007));
+    }
+
+    #[test]
+    fn invalid_technique() {
+        assert!(parse_techniques("deal with increment x").is_err());
+        assert!(parse_techniques("shuffle").is_err());
+    }
+}
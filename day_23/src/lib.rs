@@ -0,0 +1,196 @@
+use day_9::computer::{self, BoxedComputer, Computer};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+pub const COMPUTERS: usize = 50;
+pub const NAT_ADDRESS: isize = 255;
+
+pub type Packet = (isize, isize);
+
+#[derive(Debug, Snafu)]
+pub enum NetworkError {
+    #[snafu(display("NIC {} failed: {}", address, source))]
+    NicFailed {
+        address: usize,
+        source: computer::Error,
+    },
+
+    #[snafu(display("Packet sent to invalid address {}", address))]
+    AddressInvalid { address: isize },
+
+    #[snafu(display("Every NIC halted"))]
+    Halted,
+
+    #[snafu(display("The network is idle and the NAT has no packet"))]
+    Stalled,
+
+    #[snafu(display("The NAT woke the network {} times without a repeated Y", wakeups))]
+    WakeupsExhausted { wakeups: usize },
+}
+
+#[derive(Debug, Default)]
+struct Nic {
+    queue: VecDeque<isize>,
+    output: Vec<isize>,
+    /// Reads from the empty queue since the last packet was sent or received.
+    idle_reads: usize,
+    halted: bool,
+}
+
+#[derive(Debug, Default)]
+struct Network {
+    nics: Vec<Nic>,
+    nat: Option<Packet>,
+    first_nat: Option<Packet>,
+    /// The first invalid packet, which the NICs can't report themselves.
+    error: Option<NetworkError>,
+}
+
+impl Network {
+    fn send(&mut self, address: isize, packet: Packet) -> Result<(), NetworkError> {
+        if address == NAT_ADDRESS {
+            self.first_nat.get_or_insert(packet);
+            self.nat = Some(packet);
+            return Ok(());
+        }
+        ensure!(
+            (0..self.nics.len() as isize).contains(&address),
+            AddressInvalid { address }
+        );
+        let nic = &mut self.nics[address as usize];
+        nic.queue.extend(&[packet.0, packet.1]);
+        nic.idle_reads = 0;
+        Ok(())
+    }
+
+    /// Whether every NIC is either halted or waiting for packets.
+    fn is_idle(&self) -> bool {
+        self.nics
+            .iter()
+            .all(|nic| nic.halted || (nic.queue.is_empty() && nic.idle_reads > 0))
+    }
+}
+
+fn make_computer<'a>(
+    intcode: &[isize],
+    address: usize,
+    network: &Rc<RefCell<Network>>,
+) -> BoxedComputer<'a> {
+    let reader = Rc::clone(network);
+    let writer = Rc::clone(network);
+    Computer::boxed(
+        intcode.to_vec(),
+        Box::new(move || {
+            let mut network = reader.borrow_mut();
+            let nic = &mut network.nics[address];
+            nic.queue.pop_front().unwrap_or_else(|| {
+                nic.idle_reads += 1;
+                -1
+            })
+        }),
+        Box::new(move |value| {
+            let mut network = writer.borrow_mut();
+            let nic = &mut network.nics[address];
+            nic.output.push(value);
+            nic.idle_reads = 0;
+            if let [destination, x, y] = nic.output[..] {
+                nic.output.clear();
+                if let Err(error) = network.send(destination, (x, y)) {
+                    network.error.get_or_insert(error);
+                }
+            }
+        }),
+    )
+}
+
+/// Run the network until the NAT delivers the same Y twice in a row, waking
+/// it up at most `max_wakeups` times.
+///
+/// Returns the first packet sent to the NAT and the repeated one.
+pub fn simulate(intcode: &[isize], max_wakeups: usize) -> Result<(Packet, Packet), NetworkError> {
+    let network = Rc::new(RefCell::new(Network {
+        nics: (0..COMPUTERS)
+            .map(|address| Nic {
+                queue: [address as isize].iter().copied().collect(),
+                ..Nic::default()
+            })
+            .collect(),
+        ..Network::default()
+    }));
+    let mut computers: Vec<_> = (0..COMPUTERS)
+        .map(|address| make_computer(intcode, address, &network))
+        .collect();
+    let mut last_delivered: Option<Packet> = None;
+    let mut wakeups = 0;
+    loop {
+        for (address, computer) in computers.iter_mut().enumerate() {
+            if !computer.halted() && !computer.run_one().context(NicFailed { address })? {
+                network.borrow_mut().nics[address].halted = true;
+            }
+        }
+        let mut network = network.borrow_mut();
+        if let Some(error) = network.error.take() {
+            return Err(error);
+        }
+        ensure!(!network.nics.iter().all(|nic| nic.halted), Halted);
+        if network.is_idle() {
+            let packet = network.nat.context(Stalled)?;
+            if last_delivered.map(|last| last.1) == Some(packet.1) {
+                return Ok((network.first_nat.unwrap(), packet));
+            }
+            ensure!(wakeups < max_wakeups, WakeupsExhausted { wakeups });
+            network.send(0, packet)?;
+            last_delivered = Some(packet);
+            wakeups += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use day_9::asm::assemble;
+
+    #[test]
+    fn echo_to_nat() {
+        // Send (255, address, address + 7), then wait for input forever.
+        let intcode = vec![
+            3, 100, 104, 255, 4, 100, 1001, 100, 7, 101, 4, 101, 3, 102, 1105, 1, 12, 99,
+        ];
+        let (first, repeated) = simulate(&intcode, 1).unwrap();
+        assert_eq!(first, (0, 7));
+        assert_eq!(repeated, (49, 56));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(simulate(&[99], 10), Err(NetworkError::Halted)));
+        // Wait for input forever.
+        assert!(matches!(
+            simulate(&[3, 100, 1105, 1, 0], 10),
+            Err(NetworkError::Stalled)
+        ));
+        // Send (50, 0, 0).
+        assert!(matches!(
+            simulate(&[104, 50, 104, 0, 104, 0, 99], 10),
+            Err(NetworkError::AddressInvalid { address: 50 })
+        ));
+        // Send (255, 0, 0) once, then keep reading.
+        let intcode = vec![3, 100, 104, 255, 104, 0, 104, 0, 3, 100, 1105, 1, 8];
+        assert!(matches!(
+            simulate(&intcode, 0),
+            Err(NetworkError::WakeupsExhausted { wakeups: 0 })
+        ));
+        assert_eq!(simulate(&intcode, 1).unwrap(), ((0, 0), (0, 0)));
+    }
+
+    #[test]
+    fn relay_network() {
+        let intcode = assemble(include_str!("network.asm")).unwrap();
+        let (first, repeated) = simulate(&intcode, 10).unwrap();
+        assert_eq!(first.1, 1225);
+        assert_eq!(repeated.1, 2450);
+    }
+}
@@ -0,0 +1,35 @@
+; A stand-in for the puzzle input: a NIC that relays packets along the chain
+; of addresses.
+;
+; NIC 0 starts the chain by sending (0, 0) to NIC 1. Every NIC increments the
+; X of the packets it receives, adds its address to Y while X is below 100,
+; and passes them on to the next address; NIC 49 sends them to the NAT.
+        in addr
+        jnz addr, @loop
+        out @1
+        out @0
+        out @0
+loop:   in x
+        eq x, @-1, t
+        jnz t, @loop
+        in y
+        lt x, @100, t
+        mul t, addr, t
+        add y, t, y
+        add x, @1, x
+        eq addr, @49, t
+        jnz t, @nat
+        add addr, @1, dest
+        out dest
+        jz @0, @send
+nat:    out @255
+send:   out x
+        out y
+        jz @0, @loop
+
+; Data cells, overwritten before they are read.
+addr:   hlt
+x:      hlt
+y:      hlt
+t:      hlt
+dest:   hlt
@@ -0,0 +1,315 @@
+; A stand-in for the puzzle input: a small ship in the style of the droid's
+; text adventure.
+;
+;   Kitchen       Observatory
+;      |              |
+;   Hull Breach -- Hallway -- Security Checkpoint -- Pressure-Sensitive Floor
+;
+; The droid only reads the first letter of a command and of the item it names.
+; Three items are traps: the molten lava stops the droid, the giant
+; electromagnet keeps it from moving and the infinite loop never returns. The
+; floor lets the droid through when it carries the candy cane and the wreath.
+
+; Describe the current room.
+look:   add @rooms, r, ptr
+        add @look2, @0, sret
+        jz @0, @seek
+look2:  add *0, @0, arg
+        add @items, @0, ret
+        jz @0, @print
+
+; List the items in the room, if any.
+items:  add @0, @0, n
+        add @0, @0, k
+icount: eq k, ITEMS, t
+        jnz t, @ilist
+        add @locs, k, ptr
+        add @icount2, @0, sret
+        jz @0, @seek
+icount2: eq *0, r, t
+        add n, t, n
+        add k, @1, k
+        jz @0, @icount
+ilist:  jz n, @prompt
+        add @s_items, @0, arg
+        add @ilist2, @0, ret
+        jz @0, @print
+ilist2: add @0, @0, k
+iloop:  eq k, ITEMS, t
+        jnz t, @prompt
+        add @locs, k, ptr
+        add @iloop2, @0, sret
+        jz @0, @seek
+iloop2: eq *0, r, t
+        jz t, @inext
+        add @s_dash, @0, arg
+        add @iname, @0, ret
+        jz @0, @print
+iname:  add @inl, @0, nret
+        jz @0, @pname
+inl:    add @s_nl, @0, arg
+        add @inext, @0, ret
+        jz @0, @print
+inext:  add k, @1, k
+        jz @0, @iloop
+
+; Ask for a command and read it, keeping its first and sixth letter.
+prompt: add @s_prompt, @0, arg
+        add @read, @0, ret
+        jz @0, @print
+read:   add @0, @0, i
+        add @0, @0, c0
+        add @0, @0, c5
+rloop:  in c
+        eq c, @'\n', t
+        jnz t, @parse
+        jnz i, @r5
+        add c, @0, c0
+r5:     eq i, @5, t
+        jz t, @rnext
+        add c, @0, c5
+rnext:  add i, @1, i
+        jz @0, @rloop
+
+parse:  eq c0, @'t', t
+        jnz t, @take
+        eq c0, @'d', t
+        jnz t, @drop
+
+; Move north, east, south or west.
+        add @0, @0, d
+        eq c0, @'n', v
+        eq c0, @'e', t
+        add v, t, v
+        add d, t, d
+        eq c0, @'s', t
+        add v, t, v
+        mul t, @2, t
+        add d, t, d
+        eq c0, @'w', t
+        add v, t, v
+        mul t, @3, t
+        add d, t, d
+        jnz v, @move
+        add @s_command, @0, arg
+        add @prompt, @0, ret
+        jz @0, @print
+move:   add @s_stuck, @0, arg
+        add @prompt, @0, ret
+        jnz stuck, @print
+        mul r, @4, ptr
+        add ptr, d, ptr
+        add ptr, @exits, ptr
+        add @move2, @0, sret
+        jz @0, @seek
+move2:  add *0, @0, u
+        add @s_noway, @0, arg
+        eq u, @-1, t
+        jnz t, @print
+        eq u, FLOOR, t
+        jnz t, @floor
+        add u, @0, r
+        jz @0, @look
+
+; Weigh the droid and let it through, or eject it back to the checkpoint.
+floor:  add @0, @0, n
+        add @0, @0, k
+wloop:  eq k, ITEMS, t
+        jnz t, @weigh
+        add @locs, k, ptr
+        add @wloop2, @0, sret
+        jz @0, @seek
+wloop2: eq *0, @-1, u
+        add @weights, k, ptr
+        add @wloop3, @0, sret
+        jz @0, @seek
+wloop3: mul *0, u, u
+        add n, u, n
+        add k, @1, k
+        jz @0, @wloop
+weigh:  add @s_floor, @0, arg
+        add @weigh2, @0, ret
+        jz @0, @print
+weigh2: eq n, WEIGHT, t
+        jnz t, @win
+        lt n, WEIGHT, t
+        add @s_lighter, @0, arg
+        jz t, @eject
+        add @s_heavier, @0, arg
+eject:  add @look, @0, ret
+        jz @0, @print
+win:    add @s_win, @0, arg
+        add @end, @0, ret
+        jz @0, @print
+end:    hlt
+
+; Take the item in the room, setting off its trap.
+take:   add @take2, @0, found
+        jz @0, @find
+take2:  add @locs, k, ptr
+        add @take3, @0, sret
+        jz @0, @seek
+take3:  add @s_absent, @0, arg
+        add @prompt, @0, ret
+        eq *0, r, t
+        jz t, @print
+        add @-1, @0, *0
+        add @s_take, @0, arg
+        add @take4, @0, ret
+        jz @0, @print
+take4:  add @take5, @0, nret
+        jz @0, @pname
+take5:  add @s_dot, @0, arg
+        add @trap, @0, ret
+        jz @0, @print
+trap:   add @traps, k, ptr
+        add @trap2, @0, sret
+        jz @0, @seek
+trap2:  eq *0, MELT, t
+        jnz t, @melt
+        eq *0, STICK, t
+        jnz t, @stick
+        eq *0, LOOP, t
+        jnz t, @forever
+        jz @0, @prompt
+melt:   add @s_melt, @0, arg
+        add @end, @0, ret
+        jz @0, @print
+stick:  add @1, @0, stuck
+        jz @0, @prompt
+forever: jz @0, @forever
+
+; Drop a carried item in the room.
+drop:   add @drop2, @0, found
+        jz @0, @find
+drop2:  add @locs, k, ptr
+        add @drop3, @0, sret
+        jz @0, @seek
+drop3:  add @s_missing, @0, arg
+        add @prompt, @0, ret
+        eq *0, @-1, t
+        jz t, @print
+        add r, @0, *0
+        add @s_drop, @0, arg
+        add @drop4, @0, ret
+        jz @0, @print
+drop4:  add @drop5, @0, nret
+        jz @0, @pname
+drop5:  add @s_dot, @0, arg
+        add @prompt, @0, ret
+        jz @0, @print
+
+; Set `k` to the item whose name starts with `c5`, then jump to `found`.
+find:   add @0, @0, k
+floop:  add @s_absent, @0, arg
+        add @prompt, @0, ret
+        eq k, ITEMS, t
+        jnz t, @print
+        add @letters, k, ptr
+        add @fcheck, @0, sret
+        jz @0, @seek
+fcheck: eq *0, c5, t
+        jnz t, found
+        add k, @1, k
+        jz @0, @floop
+
+; Print the name of item `k`, then jump to `nret`.
+pname:  add @names, k, ptr
+        add @pname2, @0, sret
+        jz @0, @seek
+pname2: add *0, @0, arg
+        add nret, @0, ret
+        jz @0, @print
+
+; Print the NUL-terminated string at `arg`, then jump to `ret`.
+print:  add arg, @0, ptr
+        add @ploop, @0, sret
+        jz @0, @seek
+ploop:  jz *0, ret
+        out *0
+        arb @1
+        add base, @1, base
+        jz @0, @ploop
+
+; Move the relative base to `ptr`, then jump to `sret`.
+seek:   mul base, @-1, t
+        add t, ptr, t
+        arb t
+        add ptr, @0, base
+        jz @0, sret
+
+; Constants.
+ITEMS:  data 7
+FLOOR:  data 5
+WEIGHT: data 9
+MELT:   data 1
+STICK:  data 2
+LOOP:   data 3
+
+; Variables.
+r:      data 0
+base:   data 0
+stuck:  data 0
+arg:    data 0
+ret:    data 0
+nret:   data 0
+sret:   data 0
+found:  data 0
+ptr:    data 0
+c:      data 0
+c0:     data 0
+c5:     data 0
+d:      data 0
+i:      data 0
+k:      data 0
+n:      data 0
+t:      data 0
+u:      data 0
+v:      data 0
+
+; The neighbors of each room to the north, east, south and west.
+exits:  data 2, 1, -1, -1
+        data 3, 4, -1, 0
+        data -1, -1, 0, -1
+        data -1, -1, 1, -1
+        data -1, 5, -1, 1
+        data -1, -1, -1, 4
+rooms:  data s_hull, s_hallway, s_kitchen, s_observatory, s_checkpoint
+
+; The items, with the first letter of their name, the room they are in (-1
+; when the droid carries them), their weight and their trap.
+names:  data s_wreath, s_candy, s_lava, s_magnet, s_telescope, s_loop, s_ornament
+letters: data 'w', 'c', 'm', 'g', 't', 'i', 'o'
+locs:   data 0, 2, 2, 1, 3, 3, 4
+weights: data 8, 1, 0, 0, 4, 0, 2
+traps:  data 0, 0, 1, 2, 0, 3, 0
+
+s_hull: data "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here.\n\nDoors here lead:\n- north\n- east\n", 0
+s_hallway: data "\n\n\n== Hallway ==\nThis area has been optimized for something; you're just not quite sure what.\n\nDoors here lead:\n- north\n- east\n- west\n", 0
+s_kitchen: data "\n\n\n== Kitchen ==\nEverything's freeze-dried.\n\nDoors here lead:\n- south\n", 0
+s_observatory: data "\n\n\n== Observatory ==\nThere are a few telescopes; they're all bolted down, though.\n\nDoors here lead:\n- south\n", 0
+s_checkpoint: data "\n\n\n== Security Checkpoint ==\nIn the next room, a pressure-sensitive floor will verify your identity.\n\nDoors here lead:\n- east\n- west\n", 0
+s_floor: data "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- west\n\n", 0
+s_lighter: data "A loud, robotic voice says \"Alert! Droids on this ship are lighter than the detected value!\" and you are ejected back to the checkpoint.\n", 0
+s_heavier: data "A loud, robotic voice says \"Alert! Droids on this ship are heavier than the detected value!\" and you are ejected back to the checkpoint.\n", 0
+s_win:  data "A loud, robotic voice says \"Analysis complete! You may proceed.\" and you enter the cockpit.\n\"Oh, hello! You should be able to get in by typing 196872 on the keypad at the main airlock.\"\n", 0
+s_items: data "\nItems here:\n", 0
+s_dash: data "- ", 0
+s_nl:   data "\n", 0
+s_dot:  data ".\n", 0
+s_prompt: data "\nCommand?\n", 0
+s_take: data "\nYou take the ", 0
+s_drop: data "\nYou drop the ", 0
+s_absent: data "\nYou don't see that item here.\n", 0
+s_missing: data "\nYou don't have that item.\n", 0
+s_command: data "\nUnrecognized command.\n", 0
+s_noway: data "\nYou can't go that way.\n", 0
+s_stuck: data "\nThe giant electromagnet is stuck to you.  You can't move!!\n", 0
+s_melt: data "\nThe molten lava is way too hot! You melt!\n", 0
+s_wreath: data "wreath", 0
+s_candy: data "candy cane", 0
+s_lava: data "molten lava", 0
+s_magnet: data "giant electromagnet", 0
+s_telescope: data "telescope", 0
+s_loop: data "infinite loop", 0
+s_ornament: data "ornament", 0
@@ -0,0 +1,351 @@
+use day_9::computer::{self, BoxedComputer, Computer, Snapshot};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+
+const CHECKPOINT: &str = "Security Checkpoint";
+const PROMPT: &str = "Command?\n";
+/// Steps the droid may take to answer a command, so that a trap that loops
+/// forever can't hang the exploration.
+const MAX_STEPS: usize = 1_000_000;
+
+#[derive(Debug, Snafu)]
+pub enum DroidError {
+    #[snafu(display("The droid failed: {}", source))]
+    ComputerFailed { source: computer::Error },
+
+    #[snafu(display("The droid didn't answer within {} steps", steps))]
+    Unresponsive { steps: usize },
+
+    #[snafu(display("The droid didn't describe a room"))]
+    RoomMissing,
+
+    #[snafu(display("Unknown door {:?}", door))]
+    DoorUnknown { door: String },
+
+    #[snafu(display("The droid didn't find the security checkpoint"))]
+    CheckpointMissing,
+
+    #[snafu(display("The droid didn't find the pressure-sensitive floor"))]
+    FloorMissing,
+
+    #[snafu(display("No combination of items gets through the floor"))]
+    CombinationMissing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Room {
+    pub name: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+/// Parse the first room described in the output of the droid.
+pub fn parse_room(text: &str) -> Option<Room> {
+    let mut lines = text.lines().skip_while(|line| !line.starts_with("== "));
+    let name = lines.next()?.trim_matches(|c| c == '=' || c == ' ');
+    let mut room = Room {
+        name: name.to_owned(),
+        doors: Vec::new(),
+        items: Vec::new(),
+    };
+    let mut list = None;
+    for line in lines {
+        if line.starts_with("== ") || line == PROMPT.trim_end() {
+            break;
+        } else if line == "Doors here lead:" {
+            list = Some(&mut room.doors);
+        } else if line == "Items here:" {
+            list = Some(&mut room.items);
+        } else if let (Some(item), Some(list)) = (line.strip_prefix("- "), list.as_mut()) {
+            list.push(item.to_owned());
+        }
+    }
+    Some(room)
+}
+
+/// The airlock password, if the droid got through the pressure-sensitive floor.
+pub fn find_password(text: &str) -> Option<String> {
+    let start = text.find("typing ")? + "typing ".len();
+    let password: String = text[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    if password.is_empty() {
+        None
+    } else {
+        Some(password)
+    }
+}
+
+fn opposite(door: &str) -> Result<&'static str, DroidError> {
+    match door {
+        "north" => Ok("south"),
+        "south" => Ok("north"),
+        "east" => Ok("west"),
+        "west" => Ok("east"),
+        _ => DoorUnknown { door }.fail(),
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Terminal {
+    input: VecDeque<isize>,
+    output: String,
+}
+
+struct Droid {
+    computer: BoxedComputer<'static>,
+    terminal: Rc<RefCell<Terminal>>,
+}
+
+impl Droid {
+    fn new(intcode: Vec<isize>) -> Self {
+        let terminal = Rc::new(RefCell::new(Terminal::default()));
+        let reader = Rc::clone(&terminal);
+        let writer = Rc::clone(&terminal);
+        let computer = Computer::boxed(
+            intcode,
+            Box::new(move || reader.borrow_mut().input.pop_front().unwrap()),
+            Box::new(move |value| writer.borrow_mut().output.push(value as u8 as char)),
+        );
+        Self { computer, terminal }
+    }
+
+    /// Run until the droid asks for a command or stops, and return its output.
+    fn resume(&mut self) -> Result<String, DroidError> {
+        let mut steps = 0;
+        while !self.terminal.borrow().output.ends_with(PROMPT)
+            && self.computer.run_one().context(ComputerFailed)?
+        {
+            steps += 1;
+            ensure!(steps < MAX_STEPS, Unresponsive { steps });
+        }
+        Ok(std::mem::take(&mut self.terminal.borrow_mut().output))
+    }
+
+    fn send(&mut self, command: &str) -> Result<String, DroidError> {
+        self.terminal.borrow_mut().input.extend(
+            command
+                .bytes()
+                .chain(std::iter::once(b'\n'))
+                .map(isize::from),
+        );
+        self.resume()
+    }
+
+    /// Like `send`, but `None` if the droid stopped or stopped answering.
+    fn try_send(&mut self, command: &str) -> Result<Option<String>, DroidError> {
+        match self.send(command) {
+            Ok(_) if self.computer.halted() => Ok(None),
+            Ok(output) => Ok(Some(output)),
+            Err(DroidError::Unresponsive { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn snapshot(&self) -> DroidSnapshot {
+        DroidSnapshot {
+            computer: self.computer.snapshot(),
+            terminal: self.terminal.borrow().clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &DroidSnapshot) {
+        self.computer.restore(&snapshot.computer);
+        *self.terminal.borrow_mut() = snapshot.terminal.clone();
+    }
+}
+
+struct DroidSnapshot {
+    computer: Snapshot,
+    terminal: Terminal,
+}
+
+/// State of the exploration of the ship.
+#[derive(Default)]
+struct Explorer {
+    visited: HashSet<String>,
+    items: Vec<String>,
+    path: Vec<String>,
+    to_checkpoint: Option<Vec<String>>,
+    to_floor: Option<String>,
+}
+
+impl Explorer {
+    /// Depth-first visit of the ship from `room`, collecting the safe items and
+    /// coming back to `room`.
+    fn visit(&mut self, droid: &mut Droid, room: Room) -> Result<(), DroidError> {
+        self.visited.insert(room.name.clone());
+        for item in &room.items {
+            if take_safely(droid, item, room.doors.first())? {
+                self.items.push(item.clone());
+            }
+        }
+        if room.name == CHECKPOINT {
+            self.to_checkpoint = Some(self.path.clone());
+        }
+        for door in &room.doors {
+            let back = opposite(door)?;
+            let output = droid.send(door)?;
+            let next = match parse_room(&output) {
+                Some(next) => next,
+                None => continue,
+            };
+            if output.contains("ejected back") {
+                // The pressure-sensitive floor sends the droid back.
+                self.to_floor = Some(door.clone());
+                continue;
+            }
+            if self.visited.contains(&next.name) {
+                droid.send(back)?;
+                continue;
+            }
+            self.path.push(door.clone());
+            self.visit(droid, next)?;
+            self.path.pop();
+            droid.send(back)?;
+        }
+        Ok(())
+    }
+}
+
+/// Take `item` unless it's a trap, i.e. it stops the droid, makes it
+/// unresponsive or keeps it from going through `door`.
+///
+/// Returns whether the droid took the item.
+fn take_safely(droid: &mut Droid, item: &str, door: Option<&String>) -> Result<bool, DroidError> {
+    let before = droid.snapshot();
+    let safe = match droid.try_send(&format!("take {}", item))? {
+        None => false,
+        Some(_) => match door {
+            None => true,
+            Some(door) => {
+                let taken = droid.snapshot();
+                let moved = droid.try_send(door)?.and_then(|output| parse_room(&output));
+                droid.restore(&taken);
+                moved.is_some()
+            }
+        },
+    };
+    if !safe {
+        droid.restore(&before);
+    }
+    Ok(safe)
+}
+
+/// Explore the ship and find the airlock password.
+pub fn explore(intcode: Vec<isize>) -> Result<String, DroidError> {
+    let mut droid = Droid::new(intcode);
+    let start = parse_room(&droid.resume()?).context(RoomMissing)?;
+    let mut explorer = Explorer::default();
+    explorer.visit(&mut droid, start)?;
+    for door in explorer.to_checkpoint.as_ref().context(CheckpointMissing)? {
+        droid.send(door)?;
+    }
+    let items = &explorer.items;
+    for item in items {
+        droid.send(&format!("drop {}", item))?;
+    }
+    let to_floor = explorer.to_floor.as_ref().context(FloorMissing)?;
+    for subset in 0..(1_usize << items.len()) {
+        let carried: Vec<_> = (0..items.len())
+            .filter(|i| subset & (1 << i) != 0)
+            .map(|i| &items[i])
+            .collect();
+        for item in &carried {
+            droid.send(&format!("take {}", item))?;
+        }
+        if let Some(password) = find_password(&droid.send(to_floor)?) {
+            return Ok(password);
+        }
+        for item in &carried {
+            droid.send(&format!("drop {}", item))?;
+        }
+    }
+    CombinationMissing.fail()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use day_9::asm::assemble;
+
+    #[test]
+    fn room() {
+        let output = "\n\n\n\
+                      == Hull Breach ==\n\
+                      You got in through a hole in the floor here.\n\
+                      \n\
+                      Doors here lead:\n\
+                      - north\n\
+                      - east\n\
+                      \n\
+                      Items here:\n\
+                      - ornament\n\
+                      \n\
+                      Command?\n";
+        assert_eq!(
+            parse_room(output),
+            Some(Room {
+                name: "Hull Breach".to_owned(),
+                doors: vec!["north".to_owned(), "east".to_owned()],
+                items: vec!["ornament".to_owned()],
+            })
+        );
+        assert_eq!(parse_room("Command?\n"), None);
+    }
+
+    #[test]
+    fn password() {
+        assert_eq!(
+            find_password(
+                "\"Oh, hello! You should be able to get in by typing 2424308736 \
+                 on the keypad at the main airlock.\""
+            ),
+            Some("2424308736".to_owned())
+        );
+        assert_eq!(
+            find_password("Alert! Droids on this ship are heavier than the detected value!"),
+            None
+        );
+    }
+
+    #[test]
+    fn scripted_droid() {
+        // Print "Command?\n", read a line and print it back, then stop.
+        let mut intcode = vec![];
+        for c in PROMPT.bytes() {
+            intcode.extend(&[104, isize::from(c)]);
+        }
+        let read = intcode.len();
+        intcode.extend(&[
+            3,
+            100,
+            4,
+            100,
+            1008,
+            100,
+            10,
+            101,
+            1006,
+            101,
+            read as isize,
+            99,
+        ]);
+        let mut droid = Droid::new(intcode);
+        assert_eq!(droid.resume().unwrap(), PROMPT);
+        assert_eq!(droid.send("north").unwrap(), "north\n");
+        assert!(matches!(
+            opposite("up"),
+            Err(DroidError::DoorUnknown { .. })
+        ));
+    }
+
+    #[test]
+    fn adventure() {
+        let intcode = assemble(include_str!("adventure.asm")).unwrap();
+        assert_eq!(explore(intcode).unwrap(), "196872");
+    }
+}
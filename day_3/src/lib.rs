@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn manhattan_length(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+
+    fn step(self, direction: Direction) -> Self {
+        match direction {
+            Direction::Up => Point {
+                x: self.x,
+                y: self.y + 1,
+            },
+            Direction::Down => Point {
+                x: self.x,
+                y: self.y - 1,
+            },
+            Direction::Left => Point {
+                x: self.x - 1,
+                y: self.y,
+            },
+            Direction::Right => Point {
+                x: self.x + 1,
+                y: self.y,
+            },
+        }
+    }
+}
+
+pub fn parse_wire(s: &str) -> Vec<(Direction, usize)> {
+    s.trim()
+        .split(',')
+        .map(|segment| {
+            let direction = match segment.chars().next() {
+                Some('U') => Direction::Up,
+                Some('D') => Direction::Down,
+                Some('L') => Direction::Left,
+                Some('R') => Direction::Right,
+                _ => panic!("Invalid segment {:?}", segment),
+            };
+            (direction, segment[1..].parse().unwrap())
+        })
+        .collect()
+}
+
+/// The points visited by the wire, with the number of steps to first reach them.
+pub fn trace(wire: &[(Direction, usize)]) -> HashMap<Point, usize> {
+    let mut position = Point { x: 0, y: 0 };
+    let mut steps = 0;
+    let mut visits = HashMap::new();
+    for &(direction, length) in wire {
+        for _ in 0..length {
+            position = position.step(direction);
+            steps += 1;
+            visits.entry(position).or_insert(steps);
+        }
+    }
+    visits
+}
+
+/// Distance from the origin of the closest crossing of the two wires.
+pub fn closest_intersection(
+    wire1: &[(Direction, usize)],
+    wire2: &[(Direction, usize)],
+) -> Option<i32> {
+    let trace2 = trace(wire2);
+    trace(wire1)
+        .keys()
+        .filter(|point| trace2.contains_key(point))
+        .map(Point::manhattan_length)
+        .min()
+}
+
+/// Fewest combined steps the wires take to reach a crossing.
+pub fn fewest_steps(wire1: &[(Direction, usize)], wire2: &[(Direction, usize)]) -> Option<usize> {
+    let trace2 = trace(wire2);
+    trace(wire1)
+        .into_iter()
+        .filter_map(|(point, steps)| trace2.get(&point).map(|other| steps + other))
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_1: [&str; 2] = [
+        "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+        "U62,R66,U55,R34,D71,R55,D58,R83",
+    ];
+    const EXAMPLE_2: [&str; 2] = [
+        "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+        "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+    ];
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_wire("R8,U5,L5,D3"),
+            vec![
+                (Direction::Right, 8),
+                (Direction::Up, 5),
+                (Direction::Left, 5),
+                (Direction::Down, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_steps() {
+        let visits = trace(&parse_wire("R2,U1,L1,D1"));
+        assert_eq!(visits.len(), 4);
+        // The wire crosses itself, keep the first visit.
+        assert_eq!(visits[&Point { x: 1, y: 0 }], 1);
+        assert_eq!(visits[&Point { x: 2, y: 1 }], 3);
+    }
+
+    #[test]
+    fn examples_part_1() {
+        for (example, expected) in &[(EXAMPLE_1, 159), (EXAMPLE_2, 135)] {
+            let wire1 = parse_wire(example[0]);
+            let wire2 = parse_wire(example[1]);
+            assert_eq!(closest_intersection(&wire1, &wire2), Some(*expected));
+        }
+    }
+
+    #[test]
+    fn examples_part_2() {
+        for (example, expected) in &[(EXAMPLE_1, 610), (EXAMPLE_2, 410)] {
+            let wire1 = parse_wire(example[0]);
+            let wire2 = parse_wire(example[1]);
+            assert_eq!(fewest_steps(&wire1, &wire2), Some(*expected));
+        }
+    }
+
+    #[test]
+    fn day_3_part_1() {
+        let wires: Vec<_> = include_str!("../spec/input")
+            .lines()
+            .map(parse_wire)
+            .collect();
+        assert_eq!(closest_intersection(&wires[0], &wires[1]), Some(721));
+    }
+
+    #[test]
+    fn day_3_part_2() {
+        let wires: Vec<_> = include_str!("../spec/input")
+            .lines()
+            .map(parse_wire)
+            .collect();
+        assert_eq!(fewest_steps(&wires[0], &wires[1]), Some(7388));
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::RangeInclusive;
+
+/// The decimal digits of `n`, from the most significant.
+fn digits(n: u32) -> Vec<u32> {
+    n.to_string()
+        .chars()
+        .map(|d| d.to_digit(10).unwrap())
+        .collect()
+}
+
+/// Six digits that never decrease.
+fn is_candidate(digits: &[u32]) -> bool {
+    digits.len() == 6 && digits.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// Lengths of the groups of equal adjacent digits.
+fn group_lengths(digits: &[u32]) -> Vec<usize> {
+    let mut lengths = Vec::new();
+    let mut start = 0;
+    for end in 1..=digits.len() {
+        if end == digits.len() || digits[end] != digits[start] {
+            lengths.push(end - start);
+            start = end;
+        }
+    }
+    lengths
+}
+
+pub fn is_valid_part1(n: u32) -> bool {
+    let digits = digits(n);
+    is_candidate(&digits) && group_lengths(&digits).iter().any(|&len| len >= 2)
+}
+
+pub fn is_valid_part2(n: u32) -> bool {
+    let digits = digits(n);
+    is_candidate(&digits) && group_lengths(&digits).contains(&2)
+}
+
+pub fn count_valid(range: RangeInclusive<u32>, part2: bool) -> usize {
+    let is_valid = if part2 {
+        is_valid_part2
+    } else {
+        is_valid_part1
+    };
+    range.filter(|&n| is_valid(n)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_both() {
+        for n in &[122345, 112233, 111122] {
+            assert!(is_valid_part1(*n));
+            assert!(is_valid_part2(*n));
+        }
+    }
+
+    #[test]
+    fn valid_part1_only() {
+        for n in &[111111, 111123, 123444] {
+            assert!(is_valid_part1(*n));
+            assert!(!is_valid_part2(*n));
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for n in &[135679, 223450, 123789, 11111, 1111111] {
+            assert!(!is_valid_part1(*n));
+            assert!(!is_valid_part2(*n));
+        }
+    }
+
+    #[test]
+    fn day_4_part_1() {
+        assert_eq!(count_valid(147981..=691423, false), 1790);
+    }
+
+    #[test]
+    fn day_4_part_2() {
+        assert_eq!(count_valid(147981..=691423, true), 1206);
+    }
+}
@@ -5,7 +5,7 @@ use std::convert::TryFrom;
 pub struct Computer<R, W>
 where
     R: FnMut() -> isize,
-    W: FnMut(isize) -> (),
+    W: FnMut(isize),
 {
     pub intcode: Vec<isize>,
     pub read: R,
@@ -42,7 +42,7 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 impl<R, W> Computer<R, W>
 where
     R: FnMut() -> isize,
-    W: FnMut(isize) -> (),
+    W: FnMut(isize),
 {
     pub fn new(intcode: Vec<isize>, read: R, write: W) -> Computer<R, W> {
         Computer {
@@ -53,19 +53,58 @@ where
         }
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        loop {
-            debug!("Instruction {}", self.ip);
-            let instr = Instruction::try_from(
-                usize::try_from(self.intcode[self.ip]).context(Address { address: self.ip })?,
-            )?;
-            if instr == Instruction::Stop {
-                return Ok(());
-            }
-            if self.execute(&instr)? {
-                self.ip += 1 + instr.operands();
-            }
+    /// Run a single step of the program.
+    ///
+    /// Returns `false` when the program stops (i.e.,
+    /// [`Instruction::Stop`](enum.Instruction.html#variant.Stop) is executed).
+    pub fn run_one(&mut self) -> Result<bool> {
+        debug!("Instruction {}", self.ip);
+        let instr = self.next_instruction()?;
+        if instr == Instruction::Stop {
+            return Ok(false);
         }
+        if self.execute(&instr)? {
+            self.ip += 1 + instr.operands();
+        }
+        Ok(true)
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        while self.run_one()? {}
+        Ok(())
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Decode the instruction at the instruction pointer.
+    pub fn next_instruction(&self) -> Result<Instruction> {
+        Instruction::try_from(
+            usize::try_from(self.intcode[self.ip]).context(Address { address: self.ip })?,
+        )
+    }
+
+    /// The instruction at the instruction pointer with its operands, e.g.
+    /// `add @2, 3, 0`, where `@` marks immediate mode.
+    pub fn disassemble(&self) -> Result<String> {
+        let instr = self.next_instruction()?;
+        let modes = instr.modes();
+        let operands = self
+            .intcode
+            .iter()
+            .skip(self.ip + 1)
+            .take(instr.operands())
+            .enumerate()
+            .map(|(i, operand)| match modes.get(i) {
+                Some(Mode::Immediate) => format!("@{}", operand),
+                _ => operand.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(format!("{} {}", instr.mnemonic(), operands)
+            .trim_end()
+            .to_owned())
     }
 
     fn execute(&mut self, instr: &Instruction) -> Result<bool> {
@@ -226,6 +265,34 @@ impl Instruction {
     pub fn operands(&self) -> usize {
         self.inputs() + self.outputs()
     }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add(_, _) => "add",
+            Instruction::Mul(_, _) => "mul",
+            Instruction::Input => "in",
+            Instruction::Output(_) => "out",
+            Instruction::JumpIfTrue(_, _) => "jnz",
+            Instruction::JumpIfFalse(_, _) => "jz",
+            Instruction::LessThan(_, _) => "lt",
+            Instruction::Equals(_, _) => "eq",
+            Instruction::Stop => "hlt",
+        }
+    }
+
+    /// The modes of the inputs.
+    pub fn modes(&self) -> Vec<&Mode> {
+        match self {
+            Instruction::Add(a, b)
+            | Instruction::Mul(a, b)
+            | Instruction::JumpIfTrue(a, b)
+            | Instruction::JumpIfFalse(a, b)
+            | Instruction::LessThan(a, b)
+            | Instruction::Equals(a, b) => vec![a, b],
+            Instruction::Output(a) => vec![a],
+            Instruction::Input | Instruction::Stop => vec![],
+        }
+    }
 }
 
 impl TryFrom<usize> for Instruction {
@@ -281,25 +348,16 @@ impl TryFrom<u32> for Mode {
 
 #[cfg(test)]
 mod tests {
+    use day_9::computer::find_noun_verb_with;
     use log::info;
 
-    pub fn find_noun_verb(mut intcode: Vec<isize>, result: isize) -> Option<(usize, usize)> {
-        for noun in (0..intcode.len()).filter(|x| x % 4 != 0) {
-            intcode[1] = noun as isize;
-            for verb in (0..intcode.len()).filter(|x| x % 4 != 0) {
-                intcode[2] = verb as isize;
-                let mut computer = Computer::new(
-                    intcode.clone(),
-                    || std::unreachable!(),
-                    |_| std::unreachable!(),
-                );
-                computer.run().unwrap();
-                if computer.intcode[0] == result {
-                    return Some((noun, verb));
-                }
-            }
-        }
-        None
+    pub fn find_noun_verb(intcode: Vec<isize>, result: isize) -> Option<(usize, usize)> {
+        find_noun_verb_with(intcode, result, |intcode| {
+            let mut computer =
+                Computer::new(intcode, || std::unreachable!(), |_| std::unreachable!());
+            computer.run().unwrap();
+            computer.intcode[0]
+        })
     }
 
     use super::*;
@@ -315,6 +373,21 @@ mod tests {
         assert_eq!(computer.intcode, vec![2, 0, 0, 0, 99]);
     }
 
+    #[test]
+    fn test_step() {
+        let mut computer = Computer::new(
+            vec![1101, 2, 3, 0, 99],
+            || std::unreachable!(),
+            |_| std::unreachable!(),
+        );
+        assert_eq!(computer.disassemble().unwrap(), "add @2, @3, 0");
+        assert!(computer.run_one().unwrap());
+        assert_eq!(computer.ip(), 4);
+        assert_eq!(computer.disassemble().unwrap(), "hlt");
+        assert!(!computer.run_one().unwrap());
+        assert_eq!(computer.intcode[0], 5);
+    }
+
     #[test]
     fn test_example2() {
         let mut computer = Computer::new(
@@ -360,7 +433,7 @@ mod tests {
             .collect::<Result<_, _>>()
             .unwrap();
         intcode[1] = 12;
-        intcode[2] = 02;
+        intcode[2] = 2;
         let mut computer = Computer::new(intcode, || std::unreachable!(), |_| std::unreachable!());
         computer.run().unwrap();
         assert_eq!(computer.intcode[0], 9581917);
@@ -379,7 +452,7 @@ mod tests {
             .unwrap();
         let (noun, verb) = find_noun_verb(intcode, 19690720).unwrap();
         assert_eq!(noun, 25);
-        assert_eq!(verb, 05);
+        assert_eq!(verb, 5);
     }
 
     #[test]
@@ -1 +1 @@
-pub mod computer;
\ No newline at end of file
+pub mod computer;
@@ -5,37 +5,62 @@ extern crate log;
 mod computer;
 
 use clap::{App, Arg};
+use day_9::parse_intcode;
+use std::fs;
 use std::io::stdin;
 
 use computer::Computer;
 
 fn main() {
     let matches = App::new("day_5")
-        .version(&crate_version!()[..])
+        .version(crate_version!())
         .arg(
             Arg::with_name("intcode")
                 .help("the Intcode to run")
-                .required(true),
+                .required_unless("file"),
         )
+        .arg(
+            Arg::with_name("file")
+                .long("file")
+                .takes_value(true)
+                .conflicts_with("intcode")
+                .help("a file with the Intcode on its first line"),
+        )
+        .arg(Arg::with_name("step").long("step").help(
+            "print each instruction before running it; \
+                     press Enter to step, or type r and Enter (or end the input) to run to completion",
+        ))
         .get_matches();
     env_logger::init();
 
-    let intcode: Vec<isize> = matches
-        .value_of("intcode")
-        .unwrap()
-        .split(',')
-        .map(|x| x.parse())
-        .collect::<Result<_, _>>()
-        .unwrap();
-    Computer::new(
+    let data = match matches.value_of("file") {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => matches.value_of("intcode").unwrap().to_owned(),
+    };
+    let intcode: Vec<isize> = parse_intcode(data.lines().next().unwrap_or("")).unwrap();
+    let mut computer = Computer::new(
         intcode,
         || {
             let mut buffer = String::new();
             stdin().read_line(&mut buffer).unwrap();
-            buffer.parse().unwrap()
+            buffer.trim_end().parse().unwrap()
         },
         |v| println!("{}", v),
-    )
-    .run()
-    .unwrap();
+    );
+    if matches.is_present("step") {
+        let mut stepping = true;
+        loop {
+            if stepping {
+                eprint!("{:>5}: {} ", computer.ip(), computer.disassemble().unwrap());
+                let mut command = String::new();
+                // Run to completion at the end of the input, too.
+                stepping = stdin().read_line(&mut command).unwrap() > 0 && command.trim() != "r";
+            }
+            if !computer.run_one().unwrap() {
+                break;
+            }
+        }
+    } else {
+        computer.run().unwrap();
+    }
 }
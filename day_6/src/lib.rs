@@ -0,0 +1,93 @@
+use std::collections::{HashMap, VecDeque};
+use util::{AdjInsert, AdjList};
+
+/// Map each object to the one it orbits.
+pub fn parse_map(data: &str) -> HashMap<String, String> {
+    data.lines()
+        .map(|line| {
+            let mut objects = line.trim().split(')');
+            let parent = objects.next().unwrap();
+            let child = objects.next().expect("Missing orbiting object");
+            (child.to_owned(), parent.to_owned())
+        })
+        .collect()
+}
+
+/// Total number of direct and indirect orbits.
+pub fn total_orbits(map: &HashMap<String, String>) -> usize {
+    map.keys()
+        .map(|object| std::iter::successors(map.get(object), |parent| map.get(*parent)).count())
+        .sum()
+}
+
+/// Number of orbital transfers to move from orbiting the same object as `from`
+/// to orbiting the same object as `to`.
+pub fn transfers(map: &HashMap<String, String>, from: &str, to: &str) -> Option<usize> {
+    let mut adj = AdjList::new();
+    for (child, parent) in map {
+        adj.adj_insert(child.as_str(), parent.as_str());
+    }
+    let mut distances = HashMap::new();
+    distances.insert(from, 0);
+    let mut to_visit = VecDeque::new();
+    to_visit.push_back(from);
+    while let Some(current) = to_visit.pop_front() {
+        let distance: usize = distances[current];
+        if current == to {
+            // Neither `from` nor `to` move themselves.
+            return distance.checked_sub(2);
+        }
+        for &next in adj.get(current).into_iter().flatten() {
+            if !distances.contains_key(next) {
+                distances.insert(next, distance + 1);
+                to_visit.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "COM)B\n\
+                           B)C\n\
+                           C)D\n\
+                           D)E\n\
+                           E)F\n\
+                           B)G\n\
+                           G)H\n\
+                           D)I\n\
+                           E)J\n\
+                           J)K\n\
+                           K)L";
+
+    #[test]
+    fn example_1() {
+        let map = parse_map(EXAMPLE);
+        assert_eq!(map["D"], "C");
+        assert_eq!(total_orbits(&map), 42);
+    }
+
+    #[test]
+    fn example_2() {
+        let mut map = parse_map(EXAMPLE);
+        map.insert("YOU".to_owned(), "K".to_owned());
+        map.insert("SAN".to_owned(), "I".to_owned());
+        assert_eq!(transfers(&map, "YOU", "SAN"), Some(4));
+        assert_eq!(transfers(&map, "YOU", "NOWHERE"), None);
+    }
+
+    #[test]
+    fn day_6_part_1() {
+        let map = parse_map(include_str!("../tests/input"));
+        assert_eq!(total_orbits(&map), 204521);
+    }
+
+    #[test]
+    fn day_6_part_2() {
+        let map = parse_map(include_str!("../tests/input"));
+        assert_eq!(transfers(&map, "YOU", "SAN"), Some(307));
+    }
+}
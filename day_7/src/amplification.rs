@@ -1,7 +1,8 @@
-use day_5::computer::Computer;
+use day_9::computer::BoxedComputer;
 use log::debug;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 pub fn find_largest_output<Phases>(
     intcode: Vec<isize>,
@@ -13,46 +14,9 @@ where
     phase_settings
         .map(|phases| {
             debug!("Checking phases {:?}", phases);
-            let intcode = intcode.clone();
-
-            let (txs, rxs): (Vec<_>, Vec<_>) = phases
-                .iter()
-                .map(|code| {
-                    let (tx, rx) = mpsc::channel();
-                    tx.send(*code).unwrap();
-                    (tx, rx)
-                })
-                .unzip();
-            txs.first().unwrap().send(0).unwrap(); // Initial input.
-            let (signal_tx, signal_rx) = mpsc::channel();
-
-            rayon::scope(move |s| {
-                // Use the next channel to transmit.
-                for (rx, tx) in rxs.into_iter().zip(txs.into_iter().cycle().skip(1)) {
-                    let intcode = intcode.clone();
-                    let signal_tx = signal_tx.clone();
-                    s.spawn(move |_| {
-                        Computer::new(
-                            intcode,
-                            || {
-                                debug!("Receiving");
-                                let v = rx.recv_timeout(Duration::from_secs(1)).unwrap();
-                                debug!("Received {}", v);
-                                v
-                            },
-                            |v| {
-                                debug!("Sending {}", v);
-                                let _ = tx.send(v);
-                                signal_tx.send(v).unwrap();
-                            },
-                        )
-                        .run()
-                        .unwrap();
-                    })
-                }
-            });
-
-            let signal = signal_rx.iter().last().expect("No output");
+            let signal = AmplifierChain::new(intcode.clone(), &phases)
+                .last()
+                .expect("No output");
             debug!("Resulting signal: {}", signal);
             (phases, signal)
         })
@@ -60,6 +24,122 @@ where
         .unwrap()
 }
 
+/// The last output of each amplifier, running the chain with the given phases.
+pub fn run_chain(intcode: Vec<isize>, phases: &[isize]) -> Vec<isize> {
+    let mut chain = AmplifierChain::new(intcode, phases);
+    chain.by_ref().for_each(drop);
+    chain
+        .last_outputs()
+        .into_iter()
+        .map(|signal| signal.expect("No output"))
+        .collect()
+}
+
+type Queue = Rc<RefCell<VecDeque<isize>>>;
+
+struct Amplifier {
+    computer: BoxedComputer<'static>,
+    input: Queue,
+    output: Rc<Cell<Option<isize>>>,
+    last_output: Option<isize>,
+    halted: bool,
+}
+
+impl Amplifier {
+    /// Whether the amplifier can't run until it gets more input.
+    fn starved(&self) -> bool {
+        self.input.borrow().is_empty() && self.computer.awaiting_input()
+    }
+}
+
+/// A ring of amplifiers, each one feeding the next, run on a single thread.
+///
+/// Iterating yields every signal sent by an amplifier, in order.
+pub struct AmplifierChain {
+    amplifiers: Vec<Amplifier>,
+    current: usize,
+}
+
+impl AmplifierChain {
+    pub fn new(intcode: Vec<isize>, phases: &[isize]) -> Self {
+        let inputs: Vec<Queue> = phases
+            .iter()
+            .map(|phase| Rc::new(RefCell::new(vec![*phase].into_iter().collect())))
+            .collect();
+        inputs.first().unwrap().borrow_mut().push_back(0); // Initial input.
+        let amplifiers = inputs
+            .iter()
+            .zip(inputs.iter().cycle().skip(1))
+            .map(|(input, next)| {
+                let output = Rc::new(Cell::new(None));
+                let read = {
+                    let input = input.clone();
+                    Box::new(move || input.borrow_mut().pop_front().unwrap())
+                        as Box<dyn FnMut() -> isize>
+                };
+                let write = {
+                    let next = next.clone();
+                    let output = output.clone();
+                    Box::new(move |v| {
+                        next.borrow_mut().push_back(v);
+                        output.set(Some(v));
+                    }) as Box<dyn FnMut(isize)>
+                };
+                Amplifier {
+                    computer: BoxedComputer::boxed(intcode.clone(), read, write),
+                    input: input.clone(),
+                    output,
+                    last_output: None,
+                    halted: false,
+                }
+            })
+            .collect();
+        AmplifierChain {
+            amplifiers,
+            current: 0,
+        }
+    }
+
+    /// Run the amplifiers until one of them sends a signal.
+    ///
+    /// Returns `None` once every amplifier has halted or is waiting for input.
+    pub fn step(&mut self) -> Option<isize> {
+        let mut idle = 0;
+        while idle < self.amplifiers.len() {
+            let amplifier = &mut self.amplifiers[self.current];
+            if amplifier.halted || amplifier.starved() {
+                self.current = (self.current + 1) % self.amplifiers.len();
+                idle += 1;
+                continue;
+            }
+            idle = 0;
+            amplifier.halted = !amplifier.computer.run_one().unwrap();
+            if let Some(v) = amplifier.output.take() {
+                debug!("Amplifier {} sent {}", self.current, v);
+                amplifier.last_output = Some(v);
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    /// The last signal sent by each amplifier so far.
+    pub fn last_outputs(&self) -> Vec<Option<isize>> {
+        self.amplifiers
+            .iter()
+            .map(|amplifier| amplifier.last_output)
+            .collect()
+    }
+}
+
+impl Iterator for AmplifierChain {
+    type Item = isize;
+
+    fn next(&mut self) -> Option<isize> {
+        self.step()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,6 +166,17 @@ mod tests {
         assert_eq!(signal, 43210);
     }
 
+    #[test]
+    fn test_chain() {
+        let outputs = run_chain(
+            vec![
+                3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+            ],
+            &[4, 3, 2, 1, 0],
+        );
+        assert_eq!(outputs, vec![4, 43, 432, 4321, 43210]);
+    }
+
     #[test]
     fn test_example2() {
         let (phases, signal) = find_largest_output(
@@ -141,6 +232,23 @@ mod tests {
         assert_eq!(signal, 139629729);
     }
 
+    #[test]
+    fn test_chain_signals() {
+        let mut chain = AmplifierChain::new(
+            vec![
+                3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28,
+                -1, 28, 1005, 28, 6, 99, 0, 0, 5,
+            ],
+            &[9, 8, 7, 6, 5],
+        );
+        assert_eq!(chain.step(), Some(5));
+        let signals: Vec<_> = chain.by_ref().collect();
+        assert_eq!(signals.len() % 5, 4);
+        assert_eq!(signals.last(), Some(&139629729));
+        assert_eq!(chain.step(), None);
+        assert_eq!(chain.last_outputs()[4], Some(139629729));
+    }
+
     #[test]
     fn test_example5() {
         let (phases, signal) = find_largest_output(
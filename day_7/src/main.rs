@@ -1,33 +1,37 @@
 #[macro_use]
 extern crate clap;
-extern crate day_5;
 extern crate log;
 extern crate permutator;
 
-mod amplification;
-
-use amplification::find_largest_output;
 use clap::{App, Arg};
+use day_7::amplification::find_largest_output;
+use day_9::parse_intcode;
 use permutator::Permutation;
+use std::fs;
 
 fn main() {
     let matches = App::new("day_7")
-        .version(&crate_version!()[..])
+        .version(crate_version!())
         .arg(
             Arg::with_name("intcode")
                 .help("the Amplifier Controller Software to run")
-                .required(true),
+                .required_unless("file"),
+        )
+        .arg(
+            Arg::with_name("file")
+                .long("file")
+                .takes_value(true)
+                .conflicts_with("intcode")
+                .help("a file with the Intcode on its first line"),
         )
         .get_matches();
     env_logger::init();
 
-    let intcode: Vec<isize> = matches
-        .value_of("intcode")
-        .unwrap()
-        .split(',')
-        .map(|x| x.parse())
-        .collect::<Result<_, _>>()
-        .unwrap();
+    let data = match matches.value_of("file") {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => matches.value_of("intcode").unwrap().to_owned(),
+    };
+    let intcode: Vec<isize> = parse_intcode(data.lines().next().unwrap_or("")).unwrap();
     println!(
         "{:?}",
         find_largest_output(intcode, (0..=4).collect::<Vec<isize>>().permutation())
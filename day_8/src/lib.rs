@@ -1,31 +1,49 @@
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+use snafu::{ensure, OptionExt, Snafu};
+use std::convert::TryFrom;
+use util::grid::Canvas;
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum ImageError {
+    #[snafu(display("{} pixels can't be split in layers of {}", len, layer_size))]
+    RaggedInput { len: usize, layer_size: usize },
+
+    #[snafu(display("Invalid base {} digit {:?}", radix, ch))]
+    DigitInvalid { ch: char, radix: u32 },
+
+    #[snafu(display("Invalid pixel color {}", value))]
+    ColorInvalid { value: u32 },
+}
+
+/// A color that can be stacked on top of another one.
+pub trait Superpose {
+    fn superpose_to(self, other: Self) -> Self;
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub enum Pixel {
     Black,
     White,
+    #[default]
     Transparent,
 }
 
-impl Pixel {
-    pub fn superpose_to(self, other: Pixel) -> Pixel {
+impl Superpose for Pixel {
+    fn superpose_to(self, other: Pixel) -> Pixel {
         match self {
             Pixel::Transparent => other,
             _ => self,
         }
     }
 }
+impl TryFrom<u32> for Pixel {
+    type Error = ImageError;
 
-impl Default for Pixel {
-    fn default() -> Self {
-        Pixel::Transparent
-    }
-}
-impl From<u32> for Pixel {
-    fn from(v: u32) -> Self {
-        match v {
-            0 => Pixel::Black,
-            1 => Pixel::White,
-            2 => Pixel::Transparent,
-            _ => panic!("Invalid pixel color"),
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Pixel::Black),
+            1 => Ok(Pixel::White),
+            2 => Ok(Pixel::Transparent),
+            _ => Err(ImageError::ColorInvalid { value }),
         }
     }
 }
@@ -36,7 +54,7 @@ pub trait Layer {
     fn count(&self, elt: &Self::Item) -> usize;
 }
 
-type VecLayer<T> = Vec<Vec<T>>;
+pub type VecLayer<T> = Vec<Vec<T>>;
 impl<T> Layer for VecLayer<T>
 where
     T: PartialEq,
@@ -50,54 +68,101 @@ where
     }
 }
 
-pub trait Image {
-    fn read(data: &str, cols: usize, rows: usize) -> Self;
+pub trait Image: Sized {
+    type Item;
+
+    /// Read an image whose pixels are digits in the given `radix`.
+    fn try_read_radix(data: &str, cols: usize, rows: usize, radix: u32)
+        -> Result<Self, ImageError>;
+    fn try_read(data: &str, cols: usize, rows: usize) -> Result<Self, ImageError> {
+        Self::try_read_radix(data, cols, rows, 10)
+    }
+    fn read_radix(data: &str, cols: usize, rows: usize, radix: u32) -> Self {
+        Self::try_read_radix(data, cols, rows, radix).unwrap()
+    }
+    fn read(data: &str, cols: usize, rows: usize) -> Self {
+        Self::read_radix(data, cols, rows, 10)
+    }
+    /// Count of `a` times count of `b` in the layer with the fewest `select`.
+    fn checksum_with(&self, select: &Self::Item, a: &Self::Item, b: &Self::Item) -> usize;
     fn checksum(&self) -> usize;
 }
 
-type VecImage<T> = Vec<VecLayer<T>>;
+pub type VecImage<T> = Vec<VecLayer<T>>;
 impl<T> Image for VecImage<T>
 where
-    T: Default + Clone + From<u32> + PartialEq,
+    T: Default + Clone + TryFrom<u32> + PartialEq,
 {
-    fn read(data: &str, cols: usize, rows: usize) -> Self {
-        let mut result = vec![vec![vec![T::default(); cols]; rows]; data.len() / rows / cols];
-        for (i, v) in data
-            .chars()
-            .map(|c: char| c.to_digit(10).unwrap().into())
-            .enumerate()
-        {
+    type Item = T;
+
+    fn try_read_radix(
+        data: &str,
+        cols: usize,
+        rows: usize,
+        radix: u32,
+    ) -> Result<Self, ImageError> {
+        let len = data.chars().count();
+        let layer_size = rows * cols;
+        ensure!(
+            len.is_multiple_of(layer_size),
+            RaggedInput { len, layer_size }
+        );
+        let mut result = vec![vec![vec![T::default(); cols]; rows]; len / layer_size];
+        for (i, ch) in data.chars().enumerate() {
+            let v = ch
+                .to_digit(radix)
+                .and_then(|v| T::try_from(v).ok())
+                .context(DigitInvalid { ch, radix })?;
             result[i / rows / cols][(i / cols) % rows][i % cols] = v;
         }
-        result
+        Ok(result)
+    }
+
+    fn checksum_with(&self, select: &T, a: &T, b: &T) -> usize {
+        let layer = self.iter().min_by_key(|layer| layer.count(select)).unwrap();
+        layer.count(a) * layer.count(b)
     }
 
     fn checksum(&self) -> usize {
-        let layer = self
-            .iter()
-            .min_by_key(|layer| layer.count(&0.into()))
-            .unwrap();
-        layer.count(&1.into()) * layer.count(&2.into())
+        let digit = |v| T::try_from(v).ok().unwrap();
+        self.checksum_with(&digit(0), &digit(1), &digit(2))
     }
 }
 
-trait Decode<T>
+pub trait Decode<T>
 where
     T: Layer,
 {
-    fn decode(&self) -> T;
+    /// Stack the layers with `over`, which composites a pixel of the layers
+    /// above onto one of the layer below.
+    fn decode_with<F>(&self, over: F) -> T
+    where
+        F: Fn(&T::Item, &T::Item) -> T::Item;
+
+    fn decode(&self) -> T
+    where
+        T::Item: Superpose + Clone,
+    {
+        self.decode_with(|top, bottom| top.clone().superpose_to(bottom.clone()))
+    }
 }
 
-impl Decode<VecLayer<Pixel>> for VecImage<Pixel> {
-    fn decode(&self) -> VecLayer<Pixel> {
+impl<T> Decode<VecLayer<T>> for VecImage<T>
+where
+    T: Clone + PartialEq,
+{
+    fn decode_with<F>(&self, over: F) -> VecLayer<T>
+    where
+        F: Fn(&T, &T) -> T,
+    {
         self.iter().skip(1).fold(self[0].clone(), |acc, layer| {
-            acc.into_iter()
+            acc.iter()
                 .zip(layer.iter())
                 .map(|(acc_row, layer_row)| {
                     acc_row
-                        .into_iter()
+                        .iter()
                         .zip(layer_row.iter())
-                        .map(|(acc_pixel, layer_pixel)| acc_pixel.superpose_to(*layer_pixel))
+                        .map(|(acc_pixel, layer_pixel)| over(acc_pixel, layer_pixel))
                         .collect()
                 })
                 .collect()
@@ -105,24 +170,27 @@ impl Decode<VecLayer<Pixel>> for VecImage<Pixel> {
     }
 }
 
-trait Draw {
-    fn draw(&self) -> String;
+pub trait Draw {
+    /// Draw with `on` for the lit pixels and `off` for the others.
+    fn draw_with(&self, on: char, off: char) -> String;
+    fn draw(&self) -> String {
+        self.draw_with('#', ' ')
+    }
 }
 
 impl Draw for VecLayer<Pixel> {
-    fn draw(&self) -> String {
-        self.iter()
-            .map(|row| {
-                row.iter()
-                    .map(|v| match v {
-                        Pixel::White => '#',
-                        Pixel::Black => ' ',
-                        Pixel::Transparent => panic!("Can't draw transparent"),
-                    })
-                    .collect::<String>()
-            })
-            .collect::<Vec<_>>()
-            .join("\n")
+    fn draw_with(&self, on: char, off: char) -> String {
+        let mut canvas = Canvas::new();
+        for (y, row) in self.iter().enumerate() {
+            for (x, v) in row.iter().enumerate() {
+                let ch = match v {
+                    Pixel::White => on,
+                    Pixel::Black | Pixel::Transparent => off,
+                };
+                canvas.set(x as isize, y as isize, ch);
+            }
+        }
+        canvas.to_string()
     }
 }
 
@@ -143,6 +211,36 @@ mod tests {
         assert_eq!(image.checksum(), 1);
     }
 
+    #[test]
+    fn custom_checksum() {
+        let image = VecImage::<u32>::read("120012111000", 3, 2);
+        assert_eq!(image.checksum(), 4);
+        assert_eq!(image.checksum_with(&2, &1, &0), 9);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(
+            VecImage::<u32>::try_read("1234567890123", 2, 2),
+            Err(ImageError::RaggedInput {
+                len: 13,
+                layer_size: 4
+            })
+        );
+        assert_eq!(
+            VecImage::<u32>::try_read("123a", 2, 2),
+            Err(ImageError::DigitInvalid { ch: 'a', radix: 10 })
+        );
+        assert_eq!(
+            VecImage::<Pixel>::try_read("0123", 2, 2),
+            Err(ImageError::DigitInvalid { ch: '3', radix: 10 })
+        );
+        assert_eq!(
+            Pixel::try_from(3),
+            Err(ImageError::ColorInvalid { value: 3 })
+        );
+    }
+
     #[test]
     fn day_8_part_1() {
         let image = VecImage::<u32>::read(include_str!("input").lines().next().unwrap(), 25, 6);
@@ -181,6 +279,64 @@ mod tests {
             ],
         );
         assert_eq!(image.decode().draw(), " #\n# ");
+        assert_eq!(image.decode().draw_with('█', '·'), "·█\n█·");
+        assert_eq!(image[0].draw_with('█', '·'), "··\n··");
+    }
+
+    #[derive(Debug, PartialEq, Clone, Copy, Default)]
+    enum Paint {
+        Red,
+        Green,
+        Blue,
+        #[default]
+        Clear,
+    }
+
+    impl Superpose for Paint {
+        fn superpose_to(self, other: Paint) -> Paint {
+            match self {
+                Paint::Clear => other,
+                _ => self,
+            }
+        }
+    }
+
+    impl From<u32> for Paint {
+        fn from(v: u32) -> Self {
+            match v {
+                0xa => Paint::Red,
+                0xb => Paint::Green,
+                0xc => Paint::Blue,
+                0xf => Paint::Clear,
+                _ => panic!("Invalid paint"),
+            }
+        }
+    }
+
+    #[test]
+    fn hex_image() {
+        let image = VecImage::<Paint>::read_radix("affcfbffacff", 2, 2, 16);
+        assert_eq!(image.len(), 3);
+        assert_eq!(
+            image.decode(),
+            vec![
+                vec![Paint::Red, Paint::Green],
+                vec![Paint::Clear, Paint::Blue]
+            ]
+        );
+        assert_eq!(
+            VecImage::<u32>::read_radix("affcfbffacff", 2, 2, 16)[0],
+            vec![vec![0xa, 0xf], vec![0xf, 0xc]]
+        );
+    }
+
+    #[test]
+    fn first_nonzero() {
+        let image = VecImage::<u32>::read("002310456700", 2, 2);
+        assert_eq!(
+            image.decode_with(|top, bottom| if *top != 0 { *top } else { *bottom }),
+            vec![vec![1, 7], vec![2, 3]]
+        );
     }
 
     #[test]
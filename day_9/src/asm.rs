@@ -0,0 +1,398 @@
+//! A tiny assembly language for intcode programs.
+//!
+//! Each line holds one instruction: a mnemonic followed by comma-separated
+//! operands. A plain operand is in position mode, `@` marks immediate mode and
+//! `*` relative mode, e.g. `mul @2, 3, *4`. A line may start with a label
+//! `name:`, which operands can use in place of the address it marks. Anything
+//! after `;` is a comment. Operands can also be characters, e.g. `out @'a'`.
+//!
+//! The `data` directive emits its comma-separated values as they are: numbers,
+//! labels, characters and strings, e.g. `data "Hi\n", 0`.
+
+use crate::computer::{self, Instruction, Mode};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, Snafu)]
+pub enum AsmError {
+    #[snafu(display("Line {}: unknown mnemonic {:?}", line, mnemonic))]
+    MnemonicUnknown { line: usize, mnemonic: String },
+
+    #[snafu(display("Line {}: expected {} operands, found {}", line, expected, found))]
+    OperandCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[snafu(display("Line {}: invalid operand {:?}", line, operand))]
+    OperandInvalid { line: usize, operand: String },
+
+    #[snafu(display("Line {}: output operand {:?} is immediate", line, operand))]
+    OutputImmediate { line: usize, operand: String },
+
+    #[snafu(display("Line {}: unterminated string", line))]
+    StringUnterminated { line: usize },
+
+    #[snafu(display("Line {}: duplicate label {:?}", line, label))]
+    LabelDuplicate { line: usize, label: String },
+
+    #[snafu(display("Negative op-code {} at {}", value, address))]
+    OpCodeNegative { address: usize, value: isize },
+
+    #[snafu(display("Invalid instruction at {}: {}", address, source))]
+    Decode {
+        address: usize,
+        source: computer::Error,
+    },
+}
+
+type Result<T, E = AsmError> = std::result::Result<T, E>;
+
+const MNEMONICS: [(&str, isize); 10] = [
+    ("add", 1),
+    ("mul", 2),
+    ("in", 3),
+    ("out", 4),
+    ("jnz", 5),
+    ("jz", 6),
+    ("lt", 7),
+    ("eq", 8),
+    ("arb", 9),
+    ("hlt", 99),
+];
+
+/// A line of assembly, without comments and labels.
+enum Statement<'a> {
+    Instruction(&'a str, Vec<&'a str>),
+    Data(Vec<DataValue<'a>>),
+}
+
+enum DataValue<'a> {
+    Char(char),
+    Operand(&'a str),
+}
+
+/// Assemble `src` into an intcode program.
+pub fn assemble(src: &str) -> Result<Vec<isize>> {
+    // Statements with their line number.
+    let mut statements = vec![];
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for (index, line) in src.lines().enumerate() {
+        let line_number = index + 1;
+        let mut line = strip_comment(line).trim();
+        if let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if !label.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+                ensure!(
+                    labels.insert(label.to_string(), address).is_none(),
+                    LabelDuplicate {
+                        line: line_number,
+                        label
+                    }
+                );
+                line = line[colon + 1..].trim();
+            }
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = match line.find(char::is_whitespace) {
+            Some(space) => (&line[..space], line[space..].trim()),
+            None => (line, ""),
+        };
+        let statement = if mnemonic == "data" {
+            let values = parse_data(operands).context(StringUnterminated { line: line_number })?;
+            address += values.len();
+            Statement::Data(values)
+        } else {
+            let operands: Vec<_> = if operands.is_empty() {
+                vec![]
+            } else {
+                operands.split(',').map(str::trim).collect()
+            };
+            address += 1 + operands.len();
+            Statement::Instruction(mnemonic, operands)
+        };
+        statements.push((line_number, statement));
+    }
+
+    let mut intcode = vec![];
+    for (line, statement) in statements {
+        let (mnemonic, operands) = match statement {
+            Statement::Instruction(mnemonic, operands) => (mnemonic, operands),
+            Statement::Data(values) => {
+                for value in values {
+                    intcode.push(match value {
+                        DataValue::Char(c) => c as isize,
+                        DataValue::Operand(operand) => match parse_operand(operand, &labels) {
+                            Some((Mode::Position, value)) => value,
+                            _ => OperandInvalid { line, operand }.fail()?,
+                        },
+                    });
+                }
+                continue;
+            }
+        };
+        let opcode = MNEMONICS
+            .iter()
+            .find(|(m, _)| *m == mnemonic)
+            .map(|(_, opcode)| *opcode)
+            .ok_or_else(|| AsmError::MnemonicUnknown {
+                line,
+                mnemonic: mnemonic.to_string(),
+            })?;
+        let instr = Instruction::try_from(opcode as usize).unwrap();
+        ensure!(
+            operands.len() == instr.operands(),
+            OperandCount {
+                line,
+                expected: instr.operands(),
+                found: operands.len()
+            }
+        );
+        let mut encoded = opcode;
+        let mut values = vec![];
+        for (i, operand) in operands.into_iter().enumerate() {
+            let (mode, value) =
+                parse_operand(operand, &labels).ok_or_else(|| AsmError::OperandInvalid {
+                    line,
+                    operand: operand.to_string(),
+                })?;
+            ensure!(
+                i < instr.inputs() || mode != Mode::Immediate,
+                OutputImmediate { line, operand }
+            );
+            encoded += mode_digit(&mode) * 10isize.pow(i as u32 + 2);
+            values.push(value);
+        }
+        intcode.push(encoded);
+        intcode.extend(values);
+    }
+    Ok(intcode)
+}
+
+/// Disassemble a program made only of instructions, one per line.
+pub fn disassemble(intcode: &[isize]) -> Result<String> {
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < intcode.len() {
+        let value = intcode[address];
+        let opcode =
+            usize::try_from(value).map_err(|_| AsmError::OpCodeNegative { address, value })?;
+        let instr = Instruction::try_from(opcode).context(Decode { address })?;
+        let mnemonic = MNEMONICS
+            .iter()
+            .find(|(_, opcode)| *opcode == value % 100)
+            .map(|(m, _)| *m)
+            .unwrap();
+        let operands: Vec<_> = modes(&instr)
+            .iter()
+            .enumerate()
+            .map(|(i, mode)| {
+                let value = intcode.get(address + 1 + i).copied().unwrap_or(0);
+                match mode {
+                    Mode::Position => format!("{}", value),
+                    Mode::Immediate => format!("@{}", value),
+                    Mode::Relative => format!("*{}", value),
+                }
+            })
+            .collect();
+        if operands.is_empty() {
+            lines.push(mnemonic.to_string());
+        } else {
+            lines.push(format!("{} {}", mnemonic, operands.join(", ")));
+        }
+        address += 1 + instr.operands();
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Remove the comment from `line`, if any, skipping the `;` in strings and
+/// characters.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), _) if c == q => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, ';') => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+/// Split the operands of `data`, or `None` if a string is unterminated.
+fn parse_data(operands: &str) -> Option<Vec<DataValue<'_>>> {
+    let mut values = vec![];
+    let mut rest = operands.trim_start();
+    while !rest.is_empty() {
+        if let Some(string) = rest.strip_prefix('"') {
+            let mut chars = string.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i,
+                    (_, '\\') => values.push(DataValue::Char(unescape(chars.next()?.1))),
+                    (_, c) => values.push(DataValue::Char(c)),
+                }
+            };
+            rest = string[end + 1..].trim_start();
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            values.push(DataValue::Operand(rest[..end].trim()));
+            rest = &rest[end..];
+        }
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+    Some(values)
+}
+
+fn unescape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        '0' => '\0',
+        _ => c,
+    }
+}
+
+fn parse_operand(operand: &str, labels: &HashMap<String, usize>) -> Option<(Mode, isize)> {
+    let (mode, value) = if let Some(value) = operand.strip_prefix('@') {
+        (Mode::Immediate, value)
+    } else if let Some(value) = operand.strip_prefix('*') {
+        (Mode::Relative, value)
+    } else {
+        (Mode::Position, operand)
+    };
+    let value = if let Some(c) = value.strip_prefix('\'').and_then(|c| c.strip_suffix('\'')) {
+        let mut chars = c.chars();
+        let c = match (chars.next()?, chars.next(), chars.next()) {
+            ('\\', Some(c), None) => unescape(c),
+            (c, None, None) => c,
+            _ => return None,
+        };
+        c as isize
+    } else {
+        match value.parse() {
+            Ok(value) => value,
+            Err(_) => *labels.get(value)? as isize,
+        }
+    };
+    Some((mode, value))
+}
+
+fn mode_digit(mode: &Mode) -> isize {
+    match mode {
+        Mode::Position => 0,
+        Mode::Immediate => 1,
+        Mode::Relative => 2,
+    }
+}
+
+fn modes(instr: &Instruction) -> Vec<Mode> {
+    match instr {
+        Instruction::Add(a, b, c)
+        | Instruction::Mul(a, b, c)
+        | Instruction::LessThan(a, b, c)
+        | Instruction::Equals(a, b, c) => vec![a.clone(), b.clone(), c.clone()],
+        Instruction::JumpIfTrue(a, b) | Instruction::JumpIfFalse(a, b) => {
+            vec![a.clone(), b.clone()]
+        }
+        Instruction::Input(a) | Instruction::Output(a) | Instruction::RelativeBase(a) => {
+            vec![a.clone()]
+        }
+        Instruction::Stop => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Computer;
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let src = "add 1, 0, 0\nmul @2, 3, *4\nin *-1\nout @7\njnz 5, @0\njz @0, *2\nlt 1, 2, 3\neq @1, @1, 0\narb @-3\nhlt";
+        let intcode = assemble(src)?;
+        assert_eq!(&intcode[..8], &[1, 1, 0, 0, 20102, 2, 3, 4]);
+        assert_eq!(disassemble(&intcode)?, src);
+        Ok(())
+    }
+
+    #[test]
+    fn labels() -> Result<()> {
+        // Count down from 3, outputting every value.
+        let src = "
+            start: out counter   ; print the counter
+                   add counter, @-1, counter
+                   jnz counter, @start
+                   hlt
+            counter:
+        ";
+        let mut intcode = assemble(src)?;
+        assert_eq!(intcode, vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99]);
+        intcode.push(3);
+        let mut outputs = vec![];
+        Computer::new(intcode, || unreachable!(), |v| outputs.push(v))
+            .run()
+            .unwrap();
+        assert_eq!(outputs, vec![3, 2, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn data() -> Result<()> {
+        let src = r#"
+            out @'h'
+            out msg     ; prints 'i'
+            hlt
+            msg: data "i;\n", 0, msg, -1
+        "#;
+        assert_eq!(
+            assemble(src)?,
+            vec![104, 104, 4, 5, 99, 105, 59, 10, 0, 5, -1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            assemble("nop"),
+            Err(AsmError::MnemonicUnknown { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble("hlt\nadd 1, 2"),
+            Err(AsmError::OperandCount {
+                line: 2,
+                expected: 3,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            assemble("in @1"),
+            Err(AsmError::OutputImmediate { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble("jz @0, @nowhere"),
+            Err(AsmError::OperandInvalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            assemble("hlt\ndata 1, \"abc"),
+            Err(AsmError::StringUnterminated { line: 2 })
+        ));
+        assert!(matches!(
+            assemble("data @1"),
+            Err(AsmError::OperandInvalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            disassemble(&[42]),
+            Err(AsmError::Decode { address: 0, .. })
+        ));
+    }
+}
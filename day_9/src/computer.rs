@@ -1,13 +1,13 @@
 use log::debug;
 use snafu::{ensure, ResultExt, Snafu};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
 pub struct Computer<R, W>
 where
     R: FnMut() -> isize,
-    W: FnMut(isize) -> (),
+    W: FnMut(isize),
 {
     pub intcode: Vec<isize>,
     pub read: R,
@@ -15,6 +15,57 @@ where
     ip: usize,
     rb: isize,
     vmem: RefCell<HashMap<usize, isize>>,
+    tracer: Option<Box<dyn FnMut(TraceEvent)>>,
+    halted: bool,
+    breakpoints: HashSet<usize>,
+    /// The breakpoint the computer is paused at, so that it can be resumed.
+    paused_at: Option<usize>,
+    /// Outputs collected instead of being written, while running with
+    /// `run_collecting`.
+    collected: Option<Vec<isize>>,
+}
+
+/// The state of the program of a [`Computer`](struct.Computer.html), to go
+/// back to it with
+/// [`Computer::restore`](struct.Computer.html#method.restore).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    intcode: Vec<isize>,
+    ip: usize,
+    rb: isize,
+    vmem: HashMap<usize, isize>,
+    halted: bool,
+}
+
+/// Why [`Computer::run_until_event`](struct.Computer.html#method.run_until_event)
+/// or [`Computer::run_collecting`](struct.Computer.html#method.run_collecting)
+/// returned.
+///
+/// `run_until_event` only stops with `Halted` or `Breakpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunEvent {
+    /// The program executed
+    /// [`Instruction::Stop`](enum.Instruction.html#variant.Stop).
+    Halted,
+    /// The instruction pointer reached a breakpoint, before executing it.
+    Breakpoint(usize),
+    /// The next instruction reads an input. Only returned by `run_collecting`.
+    AwaitingInput,
+    /// An instruction produced an output. Only returned by `run_collecting`.
+    Output,
+}
+
+/// Events reported to the tracer of a [`Computer`](struct.Computer.html).
+#[derive(Debug, PartialEq)]
+pub enum TraceEvent {
+    /// An instruction is about to be executed.
+    Execute {
+        ip: usize,
+        instruction: Instruction,
+        rb: isize,
+    },
+    /// A value was written to memory.
+    Store { address: usize, value: isize },
 }
 
 #[derive(Debug, Snafu)]
@@ -43,10 +94,24 @@ pub enum Error {
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// A computer with type-erased IO, so that computers with different IO can be
+/// stored together.
+pub type BoxedComputer<'a> = Computer<Box<dyn FnMut() -> isize + 'a>, Box<dyn FnMut(isize) + 'a>>;
+
+impl<'a> BoxedComputer<'a> {
+    pub fn boxed(
+        intcode: Vec<isize>,
+        read: Box<dyn FnMut() -> isize + 'a>,
+        write: Box<dyn FnMut(isize) + 'a>,
+    ) -> Self {
+        Computer::new(intcode, read, write)
+    }
+}
+
 impl<R, W> Computer<R, W>
 where
     R: FnMut() -> isize,
-    W: FnMut(isize) -> (),
+    W: FnMut(isize),
 {
     pub fn new(intcode: Vec<isize>, read: R, write: W) -> Computer<R, W> {
         Computer {
@@ -56,27 +121,154 @@ where
             ip: 0,
             rb: 0,
             vmem: RefCell::new(HashMap::new()),
+            tracer: None,
+            halted: false,
+            breakpoints: HashSet::new(),
+            paused_at: None,
+            collected: None,
         }
     }
 
+    /// Report every executed instruction and memory write to `tracer`.
+    pub fn set_tracer(&mut self, tracer: impl FnMut(TraceEvent) + 'static) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
     /// Run a single step of the program.
     ///
     /// Returns `false` when the program stops (i.e.,
     /// [`Instruction::Stop`](enum.Instruction.html#variant.Stop) is executed).
     pub fn run_one(&mut self) -> Result<bool> {
         debug!("Instruction {}", self.ip);
-        let instr = Instruction::try_from(
-            usize::try_from(self.intcode[self.ip]).context(Address { address: self.ip })?,
-        )?;
+        let instr = self.next_instruction()?;
         if instr == Instruction::Stop {
+            self.halted = true;
             return Ok(false);
         }
+        if let Some(tracer) = &mut self.tracer {
+            tracer(TraceEvent::Execute {
+                ip: self.ip,
+                instruction: instr.clone(),
+                rb: self.rb,
+            });
+        }
         if self.execute(&instr)? {
             self.ip += 1 + instr.operands();
         }
         Ok(true)
     }
 
+    pub fn relative_base(&self) -> isize {
+        self.rb
+    }
+
+    pub fn set_relative_base(&mut self, rb: isize) {
+        self.rb = rb;
+    }
+
+    /// Whether the program has executed
+    /// [`Instruction::Stop`](enum.Instruction.html#variant.Stop).
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether the next step reads an input.
+    ///
+    /// This only looks at the next instruction: it doesn't tell whether `read`
+    /// has a value ready.
+    pub fn awaiting_input(&self) -> bool {
+        !self.halted && matches!(self.next_instruction(), Ok(Instruction::Input(_)))
+    }
+
+    /// Decode the instruction at the instruction pointer, without executing it.
+    pub fn next_instruction(&self) -> Result<Instruction> {
+        Instruction::try_from(
+            usize::try_from(self.get_mem(self.ip)).context(Address { address: self.ip })?,
+        )
+    }
+
+    /// Save the memory and registers, but not the IO nor the breakpoints.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            intcode: self.intcode.clone(),
+            ip: self.ip,
+            rb: self.rb,
+            vmem: self.vmem.borrow().clone(),
+            halted: self.halted,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.intcode = snapshot.intcode.clone();
+        self.ip = snapshot.ip;
+        self.rb = snapshot.rb;
+        *self.vmem.get_mut() = snapshot.vmem.clone();
+        self.halted = snapshot.halted;
+        self.paused_at = None;
+    }
+
+    pub fn add_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.insert(ip);
+    }
+
+    pub fn clear_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.remove(&ip);
+    }
+
+    /// Run until the program stops or reaches a breakpoint.
+    ///
+    /// Calling it again after a breakpoint resumes the execution from there.
+    pub fn run_until_event(&mut self) -> Result<RunEvent> {
+        loop {
+            if self.breakpoints.contains(&self.ip) && self.paused_at != Some(self.ip) {
+                self.paused_at = Some(self.ip);
+                return Ok(RunEvent::Breakpoint(self.ip));
+            }
+            self.paused_at = None;
+            if !self.run_one()? {
+                return Ok(RunEvent::Halted);
+            }
+        }
+    }
+
+    /// Run collecting the outputs in a batch, rather than passing each one to
+    /// `write`.
+    ///
+    /// Pauses when the program stops, reaches a breakpoint or is about to
+    /// execute an input instruction, and also after every output unless
+    /// `until_halt_or_input` is set. It pauses before every input instruction,
+    /// even if `read` could supply a value, so that the caller can prepare it.
+    /// Calling it again resumes the execution from there, reading the input.
+    pub fn run_collecting(&mut self, until_halt_or_input: bool) -> Result<(RunEvent, Vec<isize>)> {
+        self.collected = Some(Vec::new());
+        let event = self.collect(until_halt_or_input);
+        let outputs = self.collected.take().unwrap();
+        Ok((event?, outputs))
+    }
+
+    fn collect(&mut self, until_halt_or_input: bool) -> Result<RunEvent> {
+        loop {
+            if self.paused_at != Some(self.ip) {
+                if self.breakpoints.contains(&self.ip) {
+                    self.paused_at = Some(self.ip);
+                    return Ok(RunEvent::Breakpoint(self.ip));
+                }
+                if self.awaiting_input() {
+                    self.paused_at = Some(self.ip);
+                    return Ok(RunEvent::AwaitingInput);
+                }
+            }
+            self.paused_at = None;
+            let outputs = self.collected.as_ref().map_or(0, Vec::len);
+            if !self.run_one()? {
+                return Ok(RunEvent::Halted);
+            }
+            if !until_halt_or_input && self.collected.as_ref().map_or(0, Vec::len) > outputs {
+                return Ok(RunEvent::Output);
+            }
+        }
+    }
+
     /// Run the whole program.
     pub fn run(&mut self) -> Result<()> {
         while self.run_one()? {}
@@ -101,7 +293,10 @@ where
             }
             Instruction::Output(mode) => {
                 let value = self.load(1, mode)?;
-                (self.write)(value);
+                match &mut self.collected {
+                    Some(outputs) => outputs.push(value),
+                    None => (self.write)(value),
+                }
                 Ok(true)
             }
             Instruction::JumpIfTrue(mode1, mode2) => {
@@ -152,16 +347,10 @@ where
         }
     }
 
+    /// Any non-negative address is a valid jump target, since code can also
+    /// live in virtual memory.
     fn check_ip(&self, raw_ip: isize) -> Result<usize> {
-        if let Ok(ip) = usize::try_from(raw_ip) {
-            if ip > self.intcode.len() {
-                Err(Error::IpInvalid { ip: raw_ip })
-            } else {
-                Ok(ip)
-            }
-        } else {
-            Err(Error::IpInvalid { ip: raw_ip })
-        }
+        usize::try_from(raw_ip).map_err(|_| Error::IpInvalid { ip: raw_ip })
     }
 
     fn load(&self, offset: usize, mode: &Mode) -> Result<isize> {
@@ -177,6 +366,9 @@ where
         let address = self.try_resolve(address, mode)?.unwrap();
         debug!("Store {} in {}", value, address);
         *self.get_mem_mut(address) = value;
+        if let Some(tracer) = &mut self.tracer {
+            tracer(TraceEvent::Store { address, value });
+        }
         Ok(())
     }
 
@@ -210,6 +402,38 @@ where
     }
 }
 
+/// Find the noun and verb (the values at addresses 1 and 2) for which the
+/// program leaves `result` at address 0.
+pub fn find_noun_verb(intcode: Vec<isize>, result: isize) -> Option<(usize, usize)> {
+    find_noun_verb_with(intcode, result, |intcode| {
+        let mut computer = Computer::new(intcode, || std::unreachable!(), |_| std::unreachable!());
+        computer.run().unwrap();
+        computer.intcode[0]
+    })
+}
+
+/// Same as `find_noun_verb`, with `run` returning the value at address 0 after
+/// running the program.
+pub fn find_noun_verb_with<F>(
+    mut intcode: Vec<isize>,
+    result: isize,
+    mut run: F,
+) -> Option<(usize, usize)>
+where
+    F: FnMut(Vec<isize>) -> isize,
+{
+    for noun in (0..intcode.len()).filter(|x| x % 4 != 0) {
+        intcode[1] = noun as isize;
+        for verb in (0..intcode.len()).filter(|x| x % 4 != 0) {
+            intcode[2] = verb as isize;
+            if run(intcode.clone()) == result {
+                return Some((noun, verb));
+            }
+        }
+    }
+    None
+}
+
 fn digits(value: usize) -> Vec<u32> {
     if value == 0 {
         return vec![];
@@ -222,7 +446,7 @@ fn digits(value: usize) -> Vec<u32> {
         .collect()
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Add(Mode, Mode, Mode),
     Mul(Mode, Mode, Mode),
@@ -306,7 +530,7 @@ impl TryFrom<usize> for Instruction {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
     Position,
     Immediate,
@@ -330,26 +554,8 @@ impl TryFrom<u32> for Mode {
 mod tests {
     use log::info;
 
-    pub fn find_noun_verb(mut intcode: Vec<isize>, result: isize) -> Option<(usize, usize)> {
-        for noun in (0..intcode.len()).filter(|x| x % 4 != 0) {
-            intcode[1] = noun as isize;
-            for verb in (0..intcode.len()).filter(|x| x % 4 != 0) {
-                intcode[2] = verb as isize;
-                let mut computer = Computer::new(
-                    intcode.clone(),
-                    || std::unreachable!(),
-                    |_| std::unreachable!(),
-                );
-                computer.run().unwrap();
-                if computer.intcode[0] == result {
-                    return Some((noun, verb));
-                }
-            }
-        }
-        None
-    }
-
     use super::*;
+    use std::rc::Rc;
 
     #[test]
     fn example1() {
@@ -362,6 +568,185 @@ mod tests {
         assert_eq!(computer.intcode, vec![2, 0, 0, 0, 99]);
     }
 
+    #[test]
+    fn jump_past_end() {
+        let mut computer = Computer::new(
+            vec![1105, 1, 3],
+            || std::unreachable!(),
+            |_| std::unreachable!(),
+        );
+        match computer.run() {
+            Err(Error::OpCodeInvalid { value: 0 }) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn execute_virtual_memory() {
+        let mut output = Vec::new();
+        let mut computer = Computer::new(
+            vec![
+                1101, 0, 104, 100, 1101, 0, 7, 101, 1101, 0, 99, 102, 1105, 1, 100,
+            ],
+            || std::unreachable!(),
+            |v| output.push(v),
+        );
+        computer.run().unwrap();
+        assert_eq!(output, vec![7]);
+    }
+
+    #[test]
+    fn peek() {
+        let mut computer = Computer::new(
+            vec![1101, 1, 2, 0, 99],
+            || std::unreachable!(),
+            |_| std::unreachable!(),
+        );
+        assert_eq!(
+            computer.next_instruction().unwrap(),
+            Instruction::Add(Mode::Immediate, Mode::Immediate, Mode::Position)
+        );
+        assert!(computer.run_one().unwrap());
+        assert_eq!(computer.next_instruction().unwrap(), Instruction::Stop);
+        assert!(!computer.halted());
+    }
+
+    #[test]
+    fn relative_base() {
+        let mut output = Vec::new();
+        let mut computer = Computer::new(
+            vec![204, 2, 99, 42],
+            || std::unreachable!(),
+            |v| output.push(v),
+        );
+        assert_eq!(computer.relative_base(), 0);
+        computer.set_relative_base(1);
+        assert_eq!(computer.relative_base(), 1);
+        computer.run().unwrap();
+        assert_eq!(output, vec![42]);
+    }
+
+    #[test]
+    fn breakpoint() {
+        let output = RefCell::new(Vec::new());
+        let mut computer = Computer::new(
+            vec![1101, 20, 22, 7, 4, 7, 99, 0],
+            || std::unreachable!(),
+            |v| output.borrow_mut().push(v),
+        );
+        computer.add_breakpoint(4);
+        computer.add_breakpoint(6);
+        assert_eq!(computer.run_until_event().unwrap(), RunEvent::Breakpoint(4));
+        assert!(output.borrow().is_empty());
+        computer.clear_breakpoint(6);
+        assert_eq!(computer.run_until_event().unwrap(), RunEvent::Halted);
+        assert_eq!(*output.borrow(), vec![42]);
+    }
+
+    #[test]
+    fn snapshot() {
+        // Output the input, doubled, then stop.
+        let mut input = 0;
+        let output = RefCell::new(Vec::new());
+        let mut computer = Computer::new(
+            vec![3, 1000, 102, 2, 1000, 1000, 4, 1000, 99],
+            || {
+                input += 1;
+                input
+            },
+            |v| output.borrow_mut().push(v),
+        );
+        let snapshot = computer.snapshot();
+        computer.run().unwrap();
+        assert!(computer.halted());
+        computer.restore(&snapshot);
+        assert!(!computer.halted());
+        computer.run().unwrap();
+        assert_eq!(*output.borrow(), vec![2, 4]);
+        assert_eq!(computer.snapshot().vmem[&1000], 4);
+    }
+
+    #[test]
+    fn collecting() {
+        let intcode = vec![104, 1, 104, 2, 104, 3, 3, 13, 4, 13, 99, 0, 0, 0];
+        let mut computer = Computer::new(intcode.clone(), || 7, |_| std::unreachable!());
+        assert_eq!(
+            computer.run_collecting(true).unwrap(),
+            (RunEvent::AwaitingInput, vec![1, 2, 3])
+        );
+        assert_eq!(
+            computer.run_collecting(true).unwrap(),
+            (RunEvent::Halted, vec![7])
+        );
+        let mut computer = Computer::new(intcode, || 7, |_| std::unreachable!());
+        assert_eq!(
+            computer.run_collecting(false).unwrap(),
+            (RunEvent::Output, vec![1])
+        );
+        assert_eq!(
+            computer.run_collecting(false).unwrap(),
+            (RunEvent::Output, vec![2])
+        );
+        computer.add_breakpoint(8);
+        assert_eq!(
+            computer.run_collecting(true).unwrap(),
+            (RunEvent::AwaitingInput, vec![3])
+        );
+        assert_eq!(
+            computer.run_collecting(true).unwrap(),
+            (RunEvent::Breakpoint(8), vec![])
+        );
+        assert_eq!(
+            computer.run_collecting(false).unwrap(),
+            (RunEvent::Output, vec![7])
+        );
+        assert_eq!(
+            computer.run_collecting(false).unwrap(),
+            (RunEvent::Halted, vec![])
+        );
+    }
+
+    #[test]
+    fn status() {
+        let mut computer = Computer::new(vec![3, 0, 99], || 7, |_| std::unreachable!());
+        assert!(computer.awaiting_input());
+        assert!(!computer.halted());
+        assert!(computer.run_one().unwrap());
+        assert!(!computer.awaiting_input());
+        assert!(!computer.halted());
+        assert!(!computer.run_one().unwrap());
+        assert!(!computer.awaiting_input());
+        assert!(computer.halted());
+        assert_eq!(computer.intcode[0], 7);
+    }
+
+    #[test]
+    fn trace() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut computer = Computer::new(
+            vec![1, 0, 0, 0, 99],
+            || std::unreachable!(),
+            |_| std::unreachable!(),
+        );
+        let trace = Rc::clone(&events);
+        computer.set_tracer(move |event| trace.borrow_mut().push(event));
+        computer.run().unwrap();
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                TraceEvent::Execute {
+                    ip: 0,
+                    instruction: Instruction::Add(Mode::Position, Mode::Position, Mode::Position),
+                    rb: 0,
+                },
+                TraceEvent::Store {
+                    address: 0,
+                    value: 2
+                },
+            ]
+        );
+    }
+
     #[test]
     fn example2() {
         let mut computer = Computer::new(
@@ -407,7 +792,7 @@ mod tests {
             .collect::<Result<_, _>>()
             .unwrap();
         intcode[1] = 12;
-        intcode[2] = 02;
+        intcode[2] = 2;
         let mut computer = Computer::new(intcode, || std::unreachable!(), |_| std::unreachable!());
         computer.run().unwrap();
         assert_eq!(computer.intcode[0], 9581917);
@@ -426,7 +811,7 @@ mod tests {
             .unwrap();
         let (noun, verb) = find_noun_verb(intcode, 19690720).unwrap();
         assert_eq!(noun, 25);
-        assert_eq!(verb, 05);
+        assert_eq!(verb, 5);
     }
 
     #[test]
@@ -716,6 +1101,30 @@ mod tests {
         assert_eq!(output, intcode[1]);
     }
 
+    #[test]
+    fn boxed() {
+        let mut first = None;
+        let mut second = Vec::new();
+        let mut computers = vec![
+            Computer::boxed(
+                vec![3, 0, 4, 0, 99],
+                Box::new(|| 7),
+                Box::new(|v| first = Some(v)),
+            ),
+            Computer::boxed(
+                vec![104, 1, 104, 2, 99],
+                Box::new(|| std::unreachable!()),
+                Box::new(|v| second.push(v)),
+            ),
+        ];
+        for computer in computers.iter_mut() {
+            computer.run().unwrap();
+        }
+        drop(computers);
+        assert_eq!(first, Some(7));
+        assert_eq!(second, vec![1, 2]);
+    }
+
     #[test]
     fn day_9_part_1() {
         // Solution for day 9 part 1.
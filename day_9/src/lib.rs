@@ -1 +1,65 @@
-pub mod computer;
\ No newline at end of file
+pub mod asm;
+pub mod computer;
+
+use snafu::{ResultExt, Snafu};
+use std::num::ParseIntError;
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum ParseError {
+    #[snafu(display("Line {}: invalid value {:?}: {}", line, token, source))]
+    Value {
+        line: usize,
+        token: String,
+        source: ParseIntError,
+    },
+}
+
+/// Parse a comma-separated intcode program that may span several lines,
+/// skipping `;` comments, like in [`asm`](asm/index.html), whitespace and empty
+/// values.
+pub fn parse_intcode(s: &str) -> Result<Vec<isize>, ParseError> {
+    let mut intcode = vec![];
+    for (index, line) in s.lines().enumerate() {
+        let line_number = index + 1;
+        let code = line.split(';').next().unwrap();
+        for token in code.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            intcode.push(token.parse().context(Value {
+                line: line_number,
+                token,
+            })?);
+        }
+    }
+    Ok(intcode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_intcode("1,0,0,3,99"), Ok(vec![1, 0, 0, 3, 99]));
+        assert_eq!(parse_intcode(" 1, -2,\t3 \n"), Ok(vec![1, -2, 3]));
+        assert_eq!(
+            parse_intcode("104,1125899906842624\r\n"),
+            Ok(vec![104, 1125899906842624])
+        );
+        let src = "
+            ; Output the input, doubled.
+            3, 9,         ; in 9
+            102, 2, 9, 9, ; mul @2, 9, 9
+            4, 9,
+            99,           ; hlt
+            0,
+        ";
+        assert_eq!(
+            parse_intcode(src),
+            Ok(vec![3, 9, 102, 2, 9, 9, 4, 9, 99, 0])
+        );
+        assert_eq!(parse_intcode("1,,2,\n\n-3\r\n"), Ok(vec![1, 2, -3]));
+        assert!(matches!(
+            parse_intcode("1,2\n3 4"),
+            Err(ParseError::Value { line: 2, .. })
+        ));
+    }
+}
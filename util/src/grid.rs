@@ -0,0 +1,378 @@
+//! Directions and coordinates shared by the robot days.
+//!
+//! Coordinates follow the screen convention: `x` grows to the east and `y`
+//! grows to the south, so moving north decreases `y`.
+
+use snafu::{ensure, Snafu};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
+use std::slice::Iter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// All the directions, clockwise from north.
+    pub fn iter() -> Iter<'static, Direction> {
+        static DIRECTIONS: [Direction; 4] = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+        DIRECTIONS.iter()
+    }
+
+    pub fn turn(self, turn: Turn) -> Self {
+        match turn {
+            Turn::Left => match self {
+                Direction::North => Direction::West,
+                Direction::East => Direction::North,
+                Direction::South => Direction::East,
+                Direction::West => Direction::South,
+            },
+            Turn::Right => match self {
+                Direction::North => Direction::East,
+                Direction::East => Direction::South,
+                Direction::South => Direction::West,
+                Direction::West => Direction::North,
+            },
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coord {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Coord {
+    pub fn manhattan(&self, other: &Coord) -> isize {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    /// The neighbor towards `dir` on a `width × height` torus with a corner at
+    /// the origin.
+    pub fn wrapping_add(self, dir: Direction, width: isize, height: isize) -> Coord {
+        let next = self + dir;
+        Coord {
+            x: next.x.rem_euclid(width),
+            y: next.y.rem_euclid(height),
+        }
+    }
+
+    /// Move `distance` steps towards `dir`, stopping at the edges of the grid
+    /// spanning from the origin to `max` (inclusive).
+    pub fn mv_clamped(self, dir: Direction, distance: isize, max: Coord) -> Coord {
+        let (x, y) = match dir {
+            Direction::North => (self.x, self.y - distance),
+            Direction::East => (self.x + distance, self.y),
+            Direction::South => (self.x, self.y + distance),
+            Direction::West => (self.x - distance, self.y),
+        };
+        Coord {
+            x: x.max(0).min(max.x),
+            y: y.max(0).min(max.y),
+        }
+    }
+
+    /// The four orthogonally adjacent coordinates, clockwise from north.
+    pub fn neighbors(&self) -> [Coord; 4] {
+        [
+            *self + Direction::North,
+            *self + Direction::East,
+            *self + Direction::South,
+            *self + Direction::West,
+        ]
+    }
+}
+
+impl Add for Coord {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Coord {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl Sub for Coord {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Coord {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Add<Direction> for Coord {
+    type Output = Self;
+
+    fn add(self, dir: Direction) -> Self {
+        match dir {
+            Direction::North => Coord {
+                x: self.x,
+                y: self.y - 1,
+            },
+            Direction::East => Coord {
+                x: self.x + 1,
+                y: self.y,
+            },
+            Direction::South => Coord {
+                x: self.x,
+                y: self.y + 1,
+            },
+            Direction::West => Coord {
+                x: self.x - 1,
+                y: self.y,
+            },
+        }
+    }
+}
+
+impl AddAssign<Direction> for Coord {
+    fn add_assign(&mut self, dir: Direction) {
+        *self = *self + dir;
+    }
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum GridError {
+    #[snafu(display("Row {} has {} cells, expected {}", row, len, expected))]
+    Ragged {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+}
+
+/// Read a rectangular grid of characters, one row per line.
+///
+/// Trailing empty lines are ignored.
+pub fn parse_grid(data: &str) -> Result<Vec<Vec<char>>, GridError> {
+    let mut rows: Vec<Vec<char>> = data.lines().map(|line| line.chars().collect()).collect();
+    while rows.last().is_some_and(Vec::is_empty) {
+        rows.pop();
+    }
+    if let Some(expected) = rows.first().map(Vec::len) {
+        for (row, cells) in rows.iter().enumerate() {
+            ensure!(
+                cells.len() == expected,
+                Ragged {
+                    row,
+                    len: cells.len(),
+                    expected
+                }
+            );
+        }
+    }
+    Ok(rows)
+}
+
+/// The smallest and largest coordinates along each axis, or `None` if there
+/// are no coordinates.
+pub fn bounds<'a>(coords: impl IntoIterator<Item = &'a Coord>) -> Option<(Coord, Coord)> {
+    coords.into_iter().fold(None, |bounds, pos| {
+        let (min, max) = bounds.unwrap_or((*pos, *pos));
+        Some((
+            Coord {
+                x: min.x.min(pos.x),
+                y: min.y.min(pos.y),
+            },
+            Coord {
+                x: max.x.max(pos.x),
+                y: max.y.max(pos.y),
+            },
+        ))
+    })
+}
+
+/// Characters placed on a grid, drawn over their bounding box with spaces for
+/// the cells that weren't set.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Canvas {
+    cells: HashMap<Coord, char>,
+}
+
+impl Canvas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, x: isize, y: isize, ch: char) {
+        self.cells.insert(Coord { x, y }, ch);
+    }
+}
+
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, max) = match bounds(self.cells.keys()) {
+            Some(bounds) => bounds,
+            None => return Ok(()),
+        };
+        for y in min.y..=max.y {
+            if y != min.y {
+                writeln!(f)?;
+            }
+            for x in min.x..=max.x {
+                let ch = self.cells.get(&Coord { x, y }).unwrap_or(&' ');
+                write!(f, "{}", ch)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convention() {
+        let origin = Coord::default();
+        assert_eq!(origin + Direction::North, Coord { x: 0, y: -1 });
+        assert_eq!(origin + Direction::East, Coord { x: 1, y: 0 });
+        assert_eq!(origin + Direction::South, Coord { x: 0, y: 1 });
+        assert_eq!(origin + Direction::West, Coord { x: -1, y: 0 });
+        assert_eq!(Direction::North.turn(Turn::Left), Direction::West);
+        assert_eq!(Direction::North.turn(Turn::Right), Direction::East);
+        for dir in Direction::iter().copied() {
+            assert_eq!(dir.turn(Turn::Left).turn(Turn::Right), dir);
+            assert_eq!(dir.turn(Turn::Right).turn(Turn::Right), dir.opposite());
+            assert_eq!(origin + dir + dir.opposite(), origin);
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Coord { x: 1, y: -2 };
+        let b = Coord { x: -3, y: 4 };
+        assert_eq!(a + b, Coord { x: -2, y: 2 });
+        assert_eq!(a - b, Coord { x: 4, y: -6 });
+        assert_eq!(a.manhattan(&b), 10);
+        assert_eq!(b.manhattan(&a), 10);
+        assert_eq!(a.manhattan(&a), 0);
+    }
+
+    #[test]
+    fn wrapping() {
+        let corner = Coord { x: 4, y: 0 };
+        assert_eq!(
+            corner.wrapping_add(Direction::East, 5, 3),
+            Coord { x: 0, y: 0 }
+        );
+        assert_eq!(
+            corner.wrapping_add(Direction::North, 5, 3),
+            Coord { x: 4, y: 2 }
+        );
+        assert_eq!(
+            corner.wrapping_add(Direction::West, 5, 3),
+            corner + Direction::West
+        );
+    }
+
+    #[test]
+    fn clamped() {
+        let max = Coord { x: 4, y: 3 };
+        let center = Coord { x: 2, y: 1 };
+        assert_eq!(
+            center.mv_clamped(Direction::North, 5, max),
+            Coord { x: 2, y: 0 }
+        );
+        assert_eq!(
+            center.mv_clamped(Direction::East, 5, max),
+            Coord { x: 4, y: 1 }
+        );
+        assert_eq!(
+            center.mv_clamped(Direction::South, 5, max),
+            Coord { x: 2, y: 3 }
+        );
+        assert_eq!(
+            center.mv_clamped(Direction::West, 5, max),
+            Coord { x: 0, y: 1 }
+        );
+        assert_eq!(
+            center.mv_clamped(Direction::East, 1, max),
+            center + Direction::East
+        );
+    }
+
+    #[test]
+    fn neighbors() {
+        let center = Coord { x: 2, y: 3 };
+        let neighbors = center.neighbors();
+        for (neighbor, dir) in neighbors.iter().zip(Direction::iter()) {
+            assert_eq!(*neighbor, center + *dir);
+            assert_eq!(center.manhattan(neighbor), 1);
+        }
+    }
+
+    #[test]
+    fn bounding_box() {
+        assert_eq!(bounds(&[]), None);
+        assert_eq!(
+            bounds(&[
+                Coord { x: 1, y: -1 },
+                Coord { x: -2, y: 3 },
+                Coord { x: 0, y: 0 }
+            ]),
+            Some((Coord { x: -2, y: -1 }, Coord { x: 1, y: 3 }))
+        );
+    }
+
+    #[test]
+    fn canvas() {
+        let mut canvas = Canvas::new();
+        assert_eq!(canvas.to_string(), "");
+        canvas.set(-2, -1, '#');
+        assert_eq!(canvas.to_string(), "#");
+        canvas.set(1, 1, 'o');
+        canvas.set(0, -1, '.');
+        assert_eq!(canvas.to_string(), "# . \n    \n   o");
+        canvas.set(0, -1, '*');
+        assert_eq!(canvas.to_string(), "# * \n    \n   o");
+    }
+
+    #[test]
+    fn grid() {
+        assert_eq!(
+            parse_grid("#.#\n.@.\n\n"),
+            Ok(vec![vec!['#', '.', '#'], vec!['.', '@', '.']])
+        );
+        assert_eq!(parse_grid(""), Ok(vec![]));
+        assert_eq!(
+            parse_grid("###\n#.\n###"),
+            Err(GridError::Ragged {
+                row: 1,
+                len: 2,
+                expected: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,99 @@
+pub mod grid;
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+use std::ops::AddAssign;
+
+pub trait AddOrInsert<K, V> {
+    fn add_or_insert(&mut self, k: K, v: V);
+}
+
+impl<K, V, S> AddOrInsert<K, V> for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: AddAssign + Default,
+    S: BuildHasher,
+{
+    fn add_or_insert(&mut self, k: K, v: V) {
+        *self.entry(k).or_default() += v;
+    }
+}
+
+/// An undirected graph, mapping each node to its neighbors.
+pub type AdjList<T> = HashMap<T, HashSet<T>>;
+
+/// Connect two nodes both ways, with `E` the data attached to the edge.
+pub trait AdjInsert<T, E = ()> {
+    fn adj_insert_with(&mut self, x: T, y: T, edge: E);
+
+    fn adj_insert(&mut self, x: T, y: T)
+    where
+        E: Default,
+    {
+        self.adj_insert_with(x, y, E::default());
+    }
+}
+
+impl<T, S1, S2> AdjInsert<T> for HashMap<T, HashSet<T, S2>, S1>
+where
+    T: Clone + Eq + Hash,
+    S1: BuildHasher,
+    S2: BuildHasher + Default,
+{
+    fn adj_insert_with(&mut self, x: T, y: T, _: ()) {
+        self.entry(x.clone()).or_default().insert(y.clone());
+        self.entry(y).or_default().insert(x);
+    }
+}
+
+/// Weighted graphs, mapping each node to its neighbors and the weight of the
+/// edge to them.
+impl<T, W, S1, S2> AdjInsert<T, W> for HashMap<T, HashMap<T, W, S2>, S1>
+where
+    T: Clone + Eq + Hash,
+    W: Clone,
+    S1: BuildHasher,
+    S2: BuildHasher + Default,
+{
+    fn adj_insert_with(&mut self, x: T, y: T, weight: W) {
+        self.entry(x.clone())
+            .or_default()
+            .insert(y.clone(), weight.clone());
+        self.entry(y).or_default().insert(x, weight);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_or_insert() {
+        let mut map = HashMap::new();
+        map.add_or_insert('a', 2);
+        map.add_or_insert('b', 1);
+        map.add_or_insert('a', 3);
+        assert_eq!(map, [('a', 5), ('b', 1)].iter().cloned().collect());
+    }
+
+    #[test]
+    fn adj_insert() {
+        let mut adj = AdjList::new();
+        adj.adj_insert(0, 1);
+        adj.adj_insert(1, 2);
+        adj.adj_insert(1, 0);
+        assert_eq!(adj[&0], [1].iter().cloned().collect());
+        assert_eq!(adj[&1], [0, 2].iter().cloned().collect());
+        assert_eq!(adj[&2], [1].iter().cloned().collect());
+    }
+
+    #[test]
+    fn adj_insert_with() {
+        let mut adj = HashMap::<_, HashMap<_, _>>::new();
+        adj.adj_insert_with('a', 'b', 3);
+        adj.adj_insert_with('b', 'c', 1);
+        assert_eq!(adj[&'a'], [('b', 3)].iter().cloned().collect());
+        assert_eq!(adj[&'b'], [('a', 3), ('c', 1)].iter().cloned().collect());
+        assert_eq!(adj[&'c'], [('b', 1)].iter().cloned().collect());
+    }
+}